@@ -0,0 +1,70 @@
+//! CPU architecture of an executable, read from its ELF, Mach-O or PE
+//! header, so a backend built for the wrong machine can be reported clearly
+//! instead of failing to spawn with an opaque OS error.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+const HEADER_READ_LEN: u64 = 4096;
+/// Reported for Mach-O universal binaries, which run on either Mac arch.
+pub const UNIVERSAL: &str = "universal";
+
+/// Architecture named like `std::env::consts::ARCH`, or `None` when the
+/// format or machine type isn't recognised.
+pub fn binary_arch(path: &Path) -> io::Result<Option<&'static str>> {
+    let mut header = Vec::new();
+    File::open(path)?
+        .take(HEADER_READ_LEN)
+        .read_to_end(&mut header)?;
+    Ok(parse_header(&header))
+}
+
+/// Whether a binary of `binary` arch is expected to run alongside this app.
+pub fn is_compatible(binary: &str) -> bool {
+    binary == std::env::consts::ARCH || binary == UNIVERSAL
+}
+
+fn parse_header(header: &[u8]) -> Option<&'static str> {
+    let u16_le = |at: usize| Some(u16::from_le_bytes(header.get(at..at + 2)?.try_into().ok()?));
+    let u32_le = |at: usize| Some(u32::from_le_bytes(header.get(at..at + 4)?.try_into().ok()?));
+
+    match header.get(..4)? {
+        [0x7f, b'E', b'L', b'F'] => {
+            let machine = match header.get(5)? {
+                2 => u16::from_be_bytes(header.get(18..20)?.try_into().ok()?),
+                _ => u16_le(18)?,
+            };
+            match machine {
+                0x03 => Some("x86"),
+                0x28 => Some("arm"),
+                0x3e => Some("x86_64"),
+                0xb7 => Some("aarch64"),
+                0xf3 => Some("riscv64"),
+                _ => None,
+            }
+        }
+        [0xcf, 0xfa, 0xed, 0xfe] | [0xce, 0xfa, 0xed, 0xfe] => match u32_le(4)? {
+            0x0000_0007 => Some("x86"),
+            0x0000_000c => Some("arm"),
+            0x0100_0007 => Some("x86_64"),
+            0x0100_000c => Some("aarch64"),
+            _ => None,
+        },
+        [0xca, 0xfe, 0xba, 0xbe] => Some(UNIVERSAL),
+        [b'M', b'Z', ..] => {
+            let pe = u32_le(0x3c)? as usize;
+            if header.get(pe..pe + 4)? != b"PE\0\0" {
+                return None;
+            }
+            match u16_le(pe + 4)? {
+                0x014c => Some("x86"),
+                0x01c4 => Some("arm"),
+                0x8664 => Some("x86_64"),
+                0xaa64 => Some("aarch64"),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,1570 @@
+//! Backend lifecycle primitives shared by the desktop shell: port selection,
+//! readiness probing and restart policy. Nothing here depends on Tauri.
+
+use crate::http_pool;
+use crate::transport::BackendStream;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Child;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DEFAULT_BACKEND_PORT: u16 = 8000;
+pub const BACKEND_PORT_ENV: &str = "PHOTOSENSE_BACKEND_PORT";
+pub const BACKEND_HOST_ENV: &str = "PHOTOSENSE_BACKEND_HOST";
+const DEFAULT_BACKEND_HOST: &str = "127.0.0.1";
+pub const BIND_HOST_ENV: &str = "PHOTOSENSE_BIND_HOST";
+pub const BIND_HOST: &str = "127.0.0.1";
+/// Responses are read into memory whole, so anything larger is refused.
+pub const MAX_RESPONSE_BYTES: u64 = 32 * 1024 * 1024;
+pub const AUTH_TOKEN_ENV: &str = "PHOTOSENSE_AUTH_TOKEN";
+const DEBUG_ENV: &str = "PHOTOSENSE_DEBUG";
+const EXTERNAL_BACKEND_ENV: &str = "PHOTOSENSE_EXTERNAL_BACKEND";
+pub const AUTO_START_ENV: &str = "PHOTOSENSE_AUTO_START";
+pub const STATUS_TITLE_ENV: &str = "PHOTOSENSE_STATUS_TITLE";
+pub const FOCUS_ON_READY_ENV: &str = "PHOTOSENSE_FOCUS_ON_READY";
+pub const SANITIZE_ENV_ENV: &str = "PHOTOSENSE_SANITIZE_ENV";
+/// Variables a sanitized backend still inherits, besides `PHOTOSENSE_*` and
+/// the `LC_*` locale settings. Compared case-insensitively for Windows.
+const INHERITED_ENV: [&str; 29] = [
+    "PATH", "HOME", "USER", "LOGNAME", "SHELL", "LANG", "LANGUAGE", "TZ", "TMPDIR", "TMP",
+    "TEMP", "XDG_RUNTIME_DIR", "XDG_CACHE_HOME", "CUDA_VISIBLE_DEVICES", "USERPROFILE",
+    "USERNAME", "HOMEDRIVE", "HOMEPATH", "APPDATA", "LOCALAPPDATA", "PROGRAMDATA",
+    "PROGRAMFILES", "SYSTEMROOT", "SYSTEMDRIVE", "WINDIR", "COMSPEC", "PATHEXT",
+    "NUMBER_OF_PROCESSORS", "PROCESSOR_ARCHITECTURE",
+];
+const HEADLESS_ENV: &str = "PHOTOSENSE_HEADLESS";
+pub const DEVICE_ENV: &str = "PHOTOSENSE_DEVICE";
+const HEADLESS_FLAG: &str = "--headless";
+const PYTHON_ENV: &str = "PHOTOSENSE_PYTHON";
+const BACKEND_DIR_ENV: &str = "PHOTOSENSE_BACKEND_DIR";
+#[cfg(windows)]
+const DEFAULT_PYTHON: &str = "python";
+#[cfg(not(windows))]
+const DEFAULT_PYTHON: &str = "python3";
+const BACKEND_ARGS_ENV: &str = "PHOTOSENSE_BACKEND_ARGS";
+const MAX_BACKEND_ARGS: usize = 32;
+const MAX_BACKEND_ARG_LEN: usize = 1024;
+pub const MAX_RESTARTS_ENV: &str = "PHOTOSENSE_MAX_RESTARTS";
+const DEFAULT_MAX_RESTARTS: u32 = 5;
+const RESTART_BACKOFF_BASE_MS: u64 = 500;
+const RESTART_BACKOFF_MAX_MS: u64 = 30_000;
+pub const STARTUP_TIMEOUT_ENV: &str = "PHOTOSENSE_STARTUP_TIMEOUT_SECS";
+const STARTUP_TIMEOUT_SECS: u64 = 60;
+const STARTUP_TIMEOUT_RANGE: (u64, u64) = (10, 1800);
+pub const STARTUP_GRACE_ENV: &str = "PHOTOSENSE_STARTUP_GRACE_SECS";
+const STARTUP_GRACE_SECS: u64 = 120;
+const STARTUP_GRACE_RANGE: (u64, u64) = (0, 3600);
+pub const GRACE_POLL_INTERVAL_MS: u64 = 5000;
+const HEALTH_PATH_ENV: &str = "PHOTOSENSE_HEALTH_PATH";
+const DEFAULT_HEALTH_PATH: &str = "/health";
+const MODELS_READY_MARKER_ENV: &str = "PHOTOSENSE_MODELS_READY_MARKER";
+const DEFAULT_MODELS_READY_MARKER: &str = "Models loaded";
+const HEALTH_TIMEOUT_ENV: &str = "PHOTOSENSE_HEALTH_TIMEOUT_SECS";
+const HEALTH_CHECK_TIMEOUT_SECS: u64 = 2;
+const HEALTH_TIMEOUT_RANGE: (u64, u64) = (1, 30);
+const POLL_INTERVAL_MIN_MS: u64 = 100;
+const POLL_INTERVAL_MAX_MS: u64 = 1000;
+/// A closed loopback port refuses immediately, so the startup poll does not
+/// need the full health timeout just to learn the port isn't open yet.
+const PORT_PROBE_TIMEOUT_MS: u64 = 250;
+/// Up to this fraction of the poll interval is added at random so repeated
+/// launches and the watchdog don't probe in lockstep.
+const POLL_JITTER_DIVISOR: u32 = 4;
+const WAIT_LOG_INTERVAL_SECS: u64 = 5;
+pub const PROXY_CONCURRENCY_ENV: &str = "PHOTOSENSE_PROXY_CONCURRENCY";
+const PROXY_CONCURRENCY: u64 = 6;
+const PROXY_CONCURRENCY_RANGE: (u64, u64) = (1, 64);
+const GRACEFUL_SHUTDOWN_ENV: &str = "PHOTOSENSE_GRACEFUL_SHUTDOWN_SECS";
+const GRACEFUL_SHUTDOWN_SECS: u64 = 5;
+const GRACEFUL_SHUTDOWN_RANGE: (u64, u64) = (1, 60);
+const PORT_RELEASE_WAIT_MS: u64 = 500;
+const HEALTH_CACHE_TTL_MS: u64 = 1000;
+#[cfg(windows)]
+pub const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+#[cfg(windows)]
+pub const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+// Set once the app is intentionally shutting down so the supervisor does not
+// treat the backend exit as a crash.
+pub static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+// Exit code of the most recent backend process, or NO_EXIT_CODE if it has not
+// exited (or was terminated by a signal).
+const NO_EXIT_CODE: i32 = i32::MIN;
+static LAST_EXIT_CODE: AtomicI32 = AtomicI32::new(NO_EXIT_CODE);
+
+static AUTH_TOKEN: OnceLock<String> = OnceLock::new();
+static BACKEND_HOST: OnceLock<String> = OnceLock::new();
+/// PID of the backend we spawned (0 if none), readable from signal handlers
+/// that can't reach Tauri state. Signal, console and panic handlers must stop
+/// the backend through this alone and never lock `BackendState`: the handler
+/// may be running while that lock is held, and would deadlock on it.
+static BACKEND_PID: AtomicU32 = AtomicU32::new(0);
+static HEALTH_PATH: OnceLock<String> = OnceLock::new();
+static MODELS_READY_MARKER: OnceLock<String> = OnceLock::new();
+static BACKEND_IPS: OnceLock<Vec<IpAddr>> = OnceLock::new();
+static PREFERRED_IP: AtomicUsize = AtomicUsize::new(0);
+/// Last health probe as (when, port, healthy).
+static HEALTH_CACHE: Mutex<Option<(Instant, u16, HealthSample)>> = Mutex::new(None);
+
+/// Per-launch secret shared with the backend so other local processes (or a
+/// web page via DNS rebinding) cannot talk to it.
+pub fn auth_token() -> &'static str {
+    AUTH_TOKEN.get_or_init(|| {
+        rand::random::<[u8; 32]>()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    })
+}
+
+pub fn record_exit_code(code: Option<i32>) {
+    LAST_EXIT_CODE.store(code.unwrap_or(NO_EXIT_CODE), Ordering::SeqCst);
+}
+
+pub fn last_exit_code() -> Option<i32> {
+    match LAST_EXIT_CODE.load(Ordering::SeqCst) {
+        NO_EXIT_CODE => None,
+        code => Some(code),
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct BackendProgress {
+    pub phase: &'static str,
+    pub elapsed_ms: u64,
+}
+
+pub fn backend_port() -> u16 {
+    let raw = match std::env::var(BACKEND_PORT_ENV) {
+        Ok(v) => v,
+        Err(_) => return DEFAULT_BACKEND_PORT,
+    };
+    match raw.trim().parse::<u16>() {
+        Ok(port) if port >= 1024 => port,
+        _ => {
+            eprintln!(
+                "[Error] Invalid {}={:?}: expected a port in 1024-65535, using {}",
+                BACKEND_PORT_ENV, raw, DEFAULT_BACKEND_PORT
+            );
+            DEFAULT_BACKEND_PORT
+        }
+    }
+}
+
+fn env_u64(name: &str) -> Option<u64> {
+    std::env::var(name).ok().and_then(|v| v.trim().parse().ok())
+}
+
+fn is_valid_host(host: &str) -> bool {
+    if host.parse::<std::net::IpAddr>().is_ok() {
+        return true;
+    }
+    !host.is_empty()
+        && host.len() <= 253
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// Host the shell talks to the backend on, from `PHOTOSENSE_BACKEND_HOST`.
+pub fn backend_host() -> &'static str {
+    BACKEND_HOST.get_or_init(|| match std::env::var(BACKEND_HOST_ENV) {
+        Ok(host) if is_valid_host(host.trim()) => host.trim().to_string(),
+        Ok(host) => {
+            eprintln!(
+                "[Error] Invalid {} value '{}', using {}",
+                BACKEND_HOST_ENV, host, DEFAULT_BACKEND_HOST
+            );
+            DEFAULT_BACKEND_HOST.to_string()
+        }
+        Err(_) => DEFAULT_BACKEND_HOST.to_string(),
+    })
+}
+
+/// Browser-facing URL for `path` on the backend, bracketing IPv6 hosts.
+pub fn backend_url(port: u16, path: &str) -> String {
+    let host = backend_host();
+    if host.contains(':') {
+        format!("http://[{}]:{}{}", host, port, path)
+    } else {
+        format!("http://{}:{}{}", host, port, path)
+    }
+}
+
+pub fn is_loopback_host(host: &str) -> bool {
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<std::net::IpAddr>()
+            .map(|ip| ip.is_loopback())
+            .unwrap_or(false)
+}
+
+/// Addresses the backend host resolves to. A loopback host covers both
+/// `127.0.0.1` and `::1`, since locked-down systems sometimes only bind one.
+fn backend_ips() -> &'static [IpAddr] {
+    BACKEND_IPS.get_or_init(|| resolve_host(backend_host()))
+}
+
+/// Resolves `host` without panicking; anything unresolvable is logged and
+/// yields no addresses, so connecting just fails.
+fn resolve_host(host: &str) -> Vec<IpAddr> {
+    use std::net::ToSocketAddrs;
+
+    if is_loopback_host(host) {
+        return vec![IpAddr::V4(Ipv4Addr::LOCALHOST), IpAddr::V6(Ipv6Addr::LOCALHOST)];
+    }
+    match (host, 0).to_socket_addrs() {
+        Ok(addrs) => {
+            let mut ips: Vec<IpAddr> = Vec::new();
+            for addr in addrs {
+                if !ips.contains(&addr.ip()) {
+                    ips.push(addr.ip());
+                }
+            }
+            ips
+        }
+        Err(e) => {
+            eprintln!("[Error] Failed to resolve backend host {}: {}", host, e);
+            Vec::new()
+        }
+    }
+}
+
+/// Connects to the first backend address that accepts, starting with the one
+/// that worked last time so the common case costs a single attempt.
+fn connect_backend(port: u16, timeout: Duration) -> Result<BackendStream, String> {
+    if let Some(connected) = BackendStream::connect_socket() {
+        return connected.map_err(|e| format!("Failed to connect to backend socket: {e}"));
+    }
+    let ips = backend_ips();
+    if ips.is_empty() {
+        return Err(format!("Backend host {} did not resolve", backend_host()));
+    }
+    let preferred = PREFERRED_IP.load(Ordering::Relaxed) % ips.len();
+    let mut last_error = None;
+    for offset in 0..ips.len() {
+        let index = (preferred + offset) % ips.len();
+        match TcpStream::connect_timeout(&SocketAddr::new(ips[index], port), timeout) {
+            Ok(stream) => {
+                PREFERRED_IP.store(index, Ordering::Relaxed);
+                return Ok(BackendStream::Tcp(stream));
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(format!(
+        "Failed to connect to backend: {}",
+        last_error.map(|e| e.to_string()).unwrap_or_default()
+    ))
+}
+
+/// `PHOTOSENSE_DEBUG=1` mirrors backend output to the terminal, even in
+/// release builds where there is normally no console.
+pub fn debug_mode() -> bool {
+    std::env::var(DEBUG_ENV)
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Whether the backend is run by the developer (e.g. `python run_api.py`)
+/// and must never be spawned, restarted or killed by the shell. A
+/// non-loopback backend host implies this.
+pub fn external_backend() -> bool {
+    let flagged = std::env::var(EXTERNAL_BACKEND_ENV)
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+    flagged || !is_loopback_host(backend_host())
+}
+
+/// Whether the shell spawns the backend at launch. Turning this off with
+/// `PHOTOSENSE_AUTO_START=0` leaves it stopped until `start_backend`.
+pub fn auto_start() -> bool {
+    std::env::var(AUTO_START_ENV)
+        .map(|v| !(v.trim() == "0" || v.trim().eq_ignore_ascii_case("false")))
+        .unwrap_or(true)
+}
+
+/// Whether the main window is brought to the front when the backend becomes
+/// ready; `PHOTOSENSE_FOCUS_ON_READY=0` leaves it where it is.
+pub fn focus_on_ready() -> bool {
+    std::env::var(FOCUS_ON_READY_ENV)
+        .map(|v| !(v.trim() == "0" || v.trim().eq_ignore_ascii_case("false")))
+        .unwrap_or(true)
+}
+
+/// Smoke-test mode, from `--headless` or `PHOTOSENSE_HEADLESS=1`: no windows,
+/// just start the backend, print the result and exit.
+pub fn headless() -> bool {
+    std::env::var(HEADLESS_ENV).as_deref() == Ok("1")
+        || std::env::args().skip(1).any(|arg| arg == HEADLESS_FLAG)
+}
+
+/// Whether the window title reflects backend status; `PHOTOSENSE_STATUS_TITLE=0`
+/// keeps it static.
+pub fn status_title_enabled() -> bool {
+    std::env::var(STATUS_TITLE_ENV)
+        .map(|v| !(v.trim() == "0" || v.trim().eq_ignore_ascii_case("false")))
+        .unwrap_or(true)
+}
+
+/// Whether the bundled backend gets a sanitized environment instead of the
+/// shell's full one; `PHOTOSENSE_SANITIZE_ENV=0` turns this off.
+pub fn sanitize_env_enabled() -> bool {
+    std::env::var(SANITIZE_ENV_ENV)
+        .map(|v| !(v.trim() == "0" || v.trim().eq_ignore_ascii_case("false")))
+        .unwrap_or(true)
+}
+
+/// The allowlisted part of the current environment. Stray `PYTHONPATH`,
+/// `PYTHONHOME` or proxy settings break the frozen interpreter in the
+/// bundle, so everything else is left out. Returns the kept variables and
+/// the names of the dropped ones.
+pub fn sanitized_env() -> (Vec<(OsString, OsString)>, Vec<String>) {
+    let mut kept = Vec::new();
+    let mut dropped = Vec::new();
+    for (name, value) in std::env::vars_os() {
+        let upper = name.to_string_lossy().to_ascii_uppercase();
+        let allowed = upper.starts_with("PHOTOSENSE_")
+            || upper.starts_with("LC_")
+            || INHERITED_ENV.contains(&upper.as_str());
+        if allowed {
+            kept.push((name, value));
+        } else {
+            dropped.push(name.to_string_lossy().to_string());
+        }
+    }
+    (kept, dropped)
+}
+
+/// Debug builds only: when `PHOTOSENSE_BACKEND_DIR` points at a source
+/// checkout, the backend runs from it with the interpreter in
+/// `PHOTOSENSE_PYTHON` instead of the bundled executable. Returns the
+/// interpreter and the source directory.
+pub fn dev_python_source() -> Option<(PathBuf, PathBuf)> {
+    if !cfg!(debug_assertions) {
+        return None;
+    }
+    let source_dir = std::env::var_os(BACKEND_DIR_ENV).filter(|dir| !dir.is_empty())?;
+    let python = std::env::var_os(PYTHON_ENV)
+        .filter(|python| !python.is_empty())
+        .unwrap_or_else(|| DEFAULT_PYTHON.into());
+    Some((PathBuf::from(python), PathBuf::from(source_dir)))
+}
+
+/// Extra command-line arguments for the backend from
+/// `PHOTOSENSE_BACKEND_ARGS`, given either as a JSON array of strings or as
+/// whitespace-separated words. Arguments with control characters or over
+/// `MAX_BACKEND_ARG_LEN` bytes are dropped, and at most `MAX_BACKEND_ARGS`
+/// are kept.
+pub fn backend_args() -> Vec<String> {
+    let raw = match std::env::var(BACKEND_ARGS_ENV) {
+        Ok(raw) if !raw.trim().is_empty() => raw,
+        _ => return Vec::new(),
+    };
+    let trimmed = raw.trim();
+    let args: Vec<String> = if trimmed.starts_with('[') {
+        match serde_json::from_str(trimmed) {
+            Ok(args) => args,
+            Err(e) => {
+                eprintln!("[Error] Invalid {} JSON, ignoring: {}", BACKEND_ARGS_ENV, e);
+                return Vec::new();
+            }
+        }
+    } else {
+        trimmed.split_whitespace().map(str::to_string).collect()
+    };
+
+    let mut kept = Vec::new();
+    for arg in args {
+        if arg.is_empty() || arg.len() > MAX_BACKEND_ARG_LEN || arg.chars().any(char::is_control) {
+            eprintln!("[Warning] Ignoring unsafe backend argument {:?}", arg);
+            continue;
+        }
+        if kept.len() == MAX_BACKEND_ARGS {
+            eprintln!(
+                "[Warning] Only the first {} backend arguments are used",
+                MAX_BACKEND_ARGS
+            );
+            break;
+        }
+        kept.push(arg);
+    }
+    kept
+}
+
+pub fn max_restart_attempts() -> u32 {
+    std::env::var(MAX_RESTARTS_ENV)
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_MAX_RESTARTS)
+}
+
+/// Proxied requests allowed in flight at once; the rest wait their turn so
+/// UI load can't crowd out the health checks.
+pub fn proxy_concurrency() -> usize {
+    let (min, max) = PROXY_CONCURRENCY_RANGE;
+    env_u64(PROXY_CONCURRENCY_ENV)
+        .unwrap_or(PROXY_CONCURRENCY)
+        .clamp(min, max) as usize
+}
+
+pub fn startup_timeout() -> Duration {
+    let (min, max) = STARTUP_TIMEOUT_RANGE;
+    Duration::from_secs(
+        env_u64(STARTUP_TIMEOUT_ENV)
+            .unwrap_or(STARTUP_TIMEOUT_SECS)
+            .clamp(min, max),
+    )
+}
+
+/// Extra time to keep polling, less often, after the startup timeout before
+/// the start counts as failed. Zero disables the grace period.
+pub fn startup_grace() -> Duration {
+    let (min, max) = STARTUP_GRACE_RANGE;
+    Duration::from_secs(
+        env_u64(STARTUP_GRACE_ENV)
+            .unwrap_or(STARTUP_GRACE_SECS)
+            .clamp(min, max),
+    )
+}
+
+pub fn health_timeout() -> Duration {
+    let (min, max) = HEALTH_TIMEOUT_RANGE;
+    Duration::from_secs(
+        env_u64(HEALTH_TIMEOUT_ENV)
+            .unwrap_or(HEALTH_CHECK_TIMEOUT_SECS)
+            .clamp(min, max),
+    )
+}
+
+pub fn restart_backoff(attempt: u32) -> Duration {
+    let factor = 1u64 << attempt.saturating_sub(1).min(16);
+    Duration::from_millis((RESTART_BACKOFF_BASE_MS * factor).min(RESTART_BACKOFF_MAX_MS))
+}
+
+pub fn graceful_shutdown_timeout() -> Duration {
+    let (min, max) = GRACEFUL_SHUTDOWN_RANGE;
+    Duration::from_secs(
+        env_u64(GRACEFUL_SHUTDOWN_ENV)
+            .unwrap_or(GRACEFUL_SHUTDOWN_SECS)
+            .clamp(min, max),
+    )
+}
+
+pub fn is_port_open(port: u16) -> bool {
+    connect_backend(port, Duration::from_secs(1)).is_ok()
+}
+
+/// Returns the machine's LAN address if the backend also answers there, which
+/// means it bound a non-loopback interface.
+pub fn exposed_lan_address(port: u16) -> Option<std::net::IpAddr> {
+    // Connecting a UDP socket sends nothing; it only selects the outbound
+    // interface so we can read its address.
+    let socket = std::net::UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:80").ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    if ip.is_loopback() || ip.is_unspecified() {
+        return None;
+    }
+    let addr = std::net::SocketAddr::new(ip, port);
+    std::net::TcpStream::connect_timeout(&addr, Duration::from_millis(500))
+        .ok()
+        .map(|_| ip)
+}
+
+pub fn find_free_port() -> Option<u16> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").ok()?;
+    listener.local_addr().ok().map(|addr| addr.port())
+}
+
+const SERVICE_MARKER: &str = "photosense";
+const HEALTHY_STATES: [&str; 2] = ["healthy", "ok"];
+const BUSY_SCAN_STATES: [&str; 3] = ["queued", "scanning", "indexing"];
+
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+pub fn http_request(
+    port: u16,
+    method: &str,
+    path: &str,
+    timeout: Duration,
+) -> Result<HttpResponse, String> {
+    http_request_with_body(port, method, path, None, timeout)
+}
+
+/// `http_request` with an optional JSON body.
+pub fn http_request_with_body(
+    port: u16,
+    method: &str,
+    path: &str,
+    body: Option<&str>,
+    timeout: Duration,
+) -> Result<HttpResponse, String> {
+    let resp = http_request_bytes(port, method, path, body, timeout)?;
+    Ok(HttpResponse {
+        status: resp.status,
+        body: String::from_utf8_lossy(&resp.body).to_string(),
+    })
+}
+
+pub struct BytesResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+/// Why `http_request_bytes` got no usable response.
+#[derive(Debug)]
+pub enum RequestError {
+    /// Could not connect, or the connection dropped before a response, as
+    /// while the backend restarts.
+    Unreachable(String),
+    /// The backend answered with something malformed or too large; asking
+    /// again would get the same.
+    BadResponse(String),
+    /// Anything else, such as a timeout.
+    Failed(String),
+}
+
+impl RequestError {
+    /// Only a backend that never answered is worth asking again.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, RequestError::Unreachable(_))
+    }
+}
+
+impl std::fmt::Display for RequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestError::Unreachable(msg)
+            | RequestError::BadResponse(msg)
+            | RequestError::Failed(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl From<RequestError> for String {
+    fn from(e: RequestError) -> String {
+        e.to_string()
+    }
+}
+
+/// `http_request_with_body` without decoding the response, for images.
+/// Connections are reused through `http_pool`; a pooled one that turns out
+/// to have been closed while idle is replaced by a fresh connection once.
+/// That resend can't tell whether the server saw the request, so only
+/// idempotent methods go out on pooled connections.
+pub fn http_request_bytes(
+    port: u16,
+    method: &str,
+    path: &str,
+    body: Option<&str>,
+    timeout: Duration,
+) -> Result<BytesResponse, RequestError> {
+    let keep_alive = http_pool::enabled();
+    let content_type = if body.is_some() { "Content-Type: application/json\r\n" } else { "" };
+    let connection = if keep_alive { "" } else { "Connection: close\r\n" };
+    let body = body.unwrap_or("");
+
+    let idempotent = IDEMPOTENT_METHODS.iter().any(|m| m.eq_ignore_ascii_case(method));
+    let mut pooled = if keep_alive && idempotent { http_pool::checkout(port) } else { None };
+    loop {
+        let reused = pooled.is_some();
+        let mut stream = match pooled.take() {
+            Some(stream) => stream,
+            None => connect_backend(port, timeout).map_err(RequestError::Unreachable)?,
+        };
+        stream.set_timeouts(timeout);
+        let request = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\nAuthorization: Bearer {}\r\n{}{}Content-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            stream.host(),
+            auth_token(),
+            content_type,
+            connection,
+            body.len(),
+            body
+        );
+        let result = stream
+            .write_all(request.as_bytes())
+            .and_then(|_| http_pool::read_response(&mut stream, MAX_RESPONSE_BYTES));
+        let resp = match result {
+            Ok(resp) => resp,
+            Err(e) if reused && is_stale(&e) => continue,
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+                return Err(RequestError::BadResponse(format!("Bad response from backend: {e}")))
+            }
+            Err(e) if is_stale(&e) || e.kind() == std::io::ErrorKind::ConnectionRefused => {
+                return Err(RequestError::Unreachable(format!("Request to backend failed: {e}")))
+            }
+            Err(e) => return Err(RequestError::Failed(format!("Request to backend failed: {e}"))),
+        };
+        if keep_alive && resp.reusable {
+            http_pool::checkin(port, stream);
+        }
+        return Ok(BytesResponse {
+            status: resp.status,
+            body: resp.body,
+        });
+    }
+}
+
+const IDEMPOTENT_METHODS: [&str; 5] = ["GET", "HEAD", "OPTIONS", "PUT", "DELETE"];
+
+/// Errors meaning the server closed an idle connection before reading our
+/// request, so sending it again on a new connection is safe.
+fn is_stale(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        std::io::ErrorKind::UnexpectedEof
+            | std::io::ErrorKind::BrokenPipe
+            | std::io::ErrorKind::ConnectionReset
+            | std::io::ErrorKind::ConnectionAborted
+    )
+}
+
+/// Text in a backend output line that means model loading has finished,
+/// overridable with `PHOTOSENSE_MODELS_READY_MARKER`.
+pub fn models_ready_marker() -> &'static str {
+    MODELS_READY_MARKER.get_or_init(|| match std::env::var(MODELS_READY_MARKER_ENV) {
+        Ok(marker) if !marker.trim().is_empty() => marker,
+        _ => DEFAULT_MODELS_READY_MARKER.to_string(),
+    })
+}
+
+/// Path of the health endpoint, overridable with `PHOTOSENSE_HEALTH_PATH`.
+pub fn health_path() -> &'static str {
+    HEALTH_PATH.get_or_init(|| match std::env::var(HEALTH_PATH_ENV) {
+        Ok(path) if path.starts_with('/') && !path.contains(char::is_whitespace) => path,
+        Ok(path) => {
+            eprintln!(
+                "[Error] Invalid {} value '{}', using {}",
+                HEALTH_PATH_ENV, path, DEFAULT_HEALTH_PATH
+            );
+            DEFAULT_HEALTH_PATH.to_string()
+        }
+        Err(_) => DEFAULT_HEALTH_PATH.to_string(),
+    })
+}
+
+/// Whether a health response body identifies a PhotoSense backend, and
+/// whether that backend reports itself as ready rather than warming up.
+fn parse_health(body: &str) -> (bool, bool) {
+    let json = match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(json) => json,
+        Err(_) => return (false, false),
+    };
+    let field = |name: &str| json.get(name).and_then(|v| v.as_str()).map(str::to_string);
+    let ours = field("service").as_deref() == Some(SERVICE_MARKER);
+    let healthy = field("status").is_some_and(|s| HEALTHY_STATES.contains(&s.as_str()));
+    (ours, healthy)
+}
+
+pub fn check_backend_health_sync(port: u16) -> bool {
+    http_request(port, "GET", health_path(), health_timeout())
+        .map(|resp| resp.status == 200 && parse_health(&resp.body).1)
+        .unwrap_or(false)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HealthSample {
+    pub healthy: bool,
+    /// Whether this came from the cache rather than a new probe.
+    pub cached: bool,
+    /// Round trip of the probe that produced the result.
+    pub latency: Duration,
+}
+
+/// Like `check_backend_health_sync`, but reuses a result for the same port
+/// that is less than `HEALTH_CACHE_TTL_MS` old so the tray, watchdog and UI
+/// don't each probe the backend. `force` always probes.
+pub fn cached_health(port: u16, force: bool) -> HealthSample {
+    if !force {
+        let cache = HEALTH_CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((at, cached_port, sample)) = *cache {
+            if cached_port == port && at.elapsed() < Duration::from_millis(HEALTH_CACHE_TTL_MS) {
+                return HealthSample {
+                    cached: true,
+                    ..sample
+                };
+            }
+        }
+    }
+    let started = Instant::now();
+    let sample = HealthSample {
+        healthy: check_backend_health_sync(port),
+        cached: false,
+        latency: started.elapsed(),
+    };
+    let mut cache = HEALTH_CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    *cache = Some((Instant::now(), port, sample));
+    sample
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Readiness {
+    Ready,
+    WrongService,
+    NotYet,
+}
+
+/// Probes the health endpoint and checks that the answer comes from a
+/// PhotoSense backend that has finished warming up, rather than whatever
+/// else might be listening on the port.
+fn is_backend_ready(port: u16, timeout: Duration) -> Readiness {
+    let resp = match http_request(port, "GET", health_path(), timeout) {
+        Ok(resp) => resp,
+        Err(_) => return Readiness::NotYet,
+    };
+    match resp.status {
+        200 => match parse_health(&resp.body) {
+            (false, _) => Readiness::WrongService,
+            (true, true) => Readiness::Ready,
+            (true, false) => Readiness::NotYet,
+        },
+        // Our backend always serves its health path and accepts our token.
+        401 | 403 | 404 => Readiness::WrongService,
+        _ => Readiness::NotYet,
+    }
+}
+
+/// What a PhotoSense backend reports about itself on its health endpoint.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendIdentity {
+    pub version: Option<String>,
+    pub pid: Option<u32>,
+}
+
+/// Identity of the PhotoSense backend on `port`, or `None` when nothing
+/// answers or the service there is not PhotoSense at all.
+pub fn identify_backend(port: u16) -> Option<BackendIdentity> {
+    let resp = http_request(port, "GET", health_path(), health_timeout()).ok()?;
+    if resp.status != 200 || !parse_health(&resp.body).0 {
+        return None;
+    }
+    let json = serde_json::from_str::<serde_json::Value>(&resp.body).ok()?;
+    Some(BackendIdentity {
+        version: json.get("version").and_then(|v| v.as_str()).map(str::to_string),
+        pid: json
+            .get("pid")
+            .and_then(|v| v.as_u64())
+            .and_then(|pid| u32::try_from(pid).ok()),
+    })
+}
+
+/// True when the service on `port` identifies itself as a PhotoSense backend
+/// rather than some unrelated server that happens to hold the port, even if
+/// it is still warming up.
+pub fn is_our_backend(port: u16) -> bool {
+    identify_backend(port).is_some()
+}
+
+/// Version string the backend reports on its health endpoint.
+pub fn backend_version(port: u16) -> Result<String, String> {
+    let resp = http_request(port, "GET", health_path(), health_timeout())?;
+    if resp.status != 200 {
+        return Err(format!("Health check returned {}", resp.status));
+    }
+    serde_json::from_str::<serde_json::Value>(&resp.body)
+        .ok()
+        .and_then(|json| json.get("version")?.as_str().map(str::to_string))
+        .ok_or_else(|| "Backend did not report a version".to_string())
+}
+
+/// Warnings from a backend that is up but running in a reduced mode, taken
+/// from the `warnings` array and `degraded` field of its health response.
+/// Empty when fully functional.
+pub fn health_warnings(port: u16) -> Result<Vec<String>, String> {
+    let resp = http_request(port, "GET", health_path(), health_timeout())?;
+    if resp.status != 200 {
+        return Err(format!("Health check returned {}", resp.status));
+    }
+    let json = serde_json::from_str::<serde_json::Value>(&resp.body)
+        .map_err(|e| format!("Invalid health response: {e}"))?;
+    let mut warnings: Vec<String> = json
+        .get("warnings")
+        .and_then(|v| v.as_array())
+        .map(|items| items.iter().filter_map(|w| w.as_str().map(str::to_string)).collect())
+        .unwrap_or_default();
+    match json.get("degraded") {
+        Some(serde_json::Value::String(reason)) => warnings.insert(0, reason.clone()),
+        Some(serde_json::Value::Bool(true)) if warnings.is_empty() => {
+            warnings.push("Backend is running in a reduced mode".to_string())
+        }
+        _ => {}
+    }
+    Ok(warnings)
+}
+
+/// Whether the backend reports a scan or indexing job in flight. Errors count
+/// as idle so an unresponsive backend never blocks quitting.
+pub fn is_indexing(port: u16) -> bool {
+    let resp = match http_request(port, "GET", "/scan/status", health_timeout()) {
+        Ok(resp) if resp.status == 200 => resp,
+        _ => return false,
+    };
+    serde_json::from_str::<serde_json::Value>(&resp.body)
+        .ok()
+        .and_then(|json| json.get("status")?.as_str().map(|s| BUSY_SCAN_STATES.contains(&s)))
+        .unwrap_or(false)
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SelfTestCheck {
+    pub ok: bool,
+    #[serde(default)]
+    pub detail: Option<String>,
+}
+
+/// Result of the backend's `/selftest`, one check per subsystem.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SelfTestResult {
+    pub ok: bool,
+    pub models: SelfTestCheck,
+    pub database: SelfTestCheck,
+    pub gpu: SelfTestCheck,
+    pub inference: SelfTestCheck,
+    pub inference_latency_ms: Option<u64>,
+}
+
+/// Asks the backend to exercise its subsystems. The sample inference may
+/// have to load a model first, hence the caller-supplied timeout.
+pub fn run_self_test(port: u16, timeout: Duration) -> Result<SelfTestResult, String> {
+    let resp = http_request(port, "POST", "/selftest", timeout)?;
+    if resp.status != 200 {
+        return Err(format!("Self-test returned {}", resp.status));
+    }
+    serde_json::from_str(&resp.body).map_err(|e| format!("Invalid self-test response: {e}"))
+}
+
+/// A successful `BackendWaiter::wait`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadyInfo {
+    pub elapsed: Duration,
+}
+
+/// Why `BackendWaiter::wait` gave up, with the time it took.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitError {
+    /// Nothing ever listened on the port: the process failed to start or
+    /// died early.
+    PortNeverOpened(Duration),
+    /// The port opened but health never passed, e.g. models still loading
+    /// or a bad config.
+    PortOpenButUnhealthy(Duration),
+    /// Something other than a PhotoSense backend answered on the port.
+    WrongService(Duration),
+}
+
+impl WaitError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            WaitError::PortNeverOpened(_) => "port_never_opened",
+            WaitError::PortOpenButUnhealthy(_) => "port_open_but_unhealthy",
+            WaitError::WrongService(_) => "wrong_service",
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        match self {
+            WaitError::PortNeverOpened(d)
+            | WaitError::PortOpenButUnhealthy(d)
+            | WaitError::WrongService(d) => *d,
+        }
+    }
+
+    /// The same error for a wait that followed one lasting `earlier`.
+    pub fn after(self, earlier: Duration) -> Self {
+        match self {
+            WaitError::PortNeverOpened(d) => WaitError::PortNeverOpened(earlier + d),
+            WaitError::PortOpenButUnhealthy(d) => WaitError::PortOpenButUnhealthy(earlier + d),
+            WaitError::WrongService(d) => WaitError::WrongService(earlier + d),
+        }
+    }
+}
+
+/// Startup poll for a freshly spawned or attached backend: a cheap TCP
+/// connect until the port opens, then health probes, with the interval
+/// doubling from `min_interval` to `max_interval` plus some jitter.
+#[derive(Debug, Clone)]
+pub struct BackendWaiter {
+    pub connect_timeout: Duration,
+    pub health_timeout: Duration,
+    pub deadline: Duration,
+    pub min_interval: Duration,
+    pub max_interval: Duration,
+}
+
+impl BackendWaiter {
+    /// Waiter that gives up after `deadline`, with the other settings taken
+    /// from the environment and defaults.
+    pub fn new(deadline: Duration) -> Self {
+        // A closed loopback port refuses immediately; remote hosts get the
+        // full health timeout to connect.
+        let connect_timeout = if is_loopback_host(backend_host()) {
+            Duration::from_millis(PORT_PROBE_TIMEOUT_MS)
+        } else {
+            health_timeout()
+        };
+        BackendWaiter {
+            connect_timeout,
+            health_timeout: health_timeout(),
+            deadline,
+            min_interval: Duration::from_millis(POLL_INTERVAL_MIN_MS),
+            max_interval: Duration::from_millis(POLL_INTERVAL_MAX_MS),
+        }
+    }
+
+    /// Never sleeps or probes past the deadline, so a slow health request
+    /// can't push the wait beyond it.
+    pub fn wait(
+        &self,
+        port: u16,
+        mut on_progress: impl FnMut(BackendProgress),
+    ) -> Result<ReadyInfo, WaitError> {
+        let started = Instant::now();
+        let deadline = started + self.deadline;
+        let mut interval = self.min_interval;
+        let mut last_report = 0;
+        let mut port_reported = false;
+        let progress = |phase| BackendProgress {
+            phase,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        };
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(if port_reported {
+                    WaitError::PortOpenButUnhealthy(started.elapsed())
+                } else {
+                    WaitError::PortNeverOpened(started.elapsed())
+                });
+            }
+            if connect_backend(port, self.connect_timeout.min(remaining)).is_ok() {
+                if !port_reported {
+                    port_reported = true;
+                    on_progress(progress("port_open"));
+                }
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                match is_backend_ready(port, self.health_timeout.min(remaining)) {
+                    Readiness::Ready => {
+                        on_progress(progress("health_ok"));
+                        return Ok(ReadyInfo {
+                            elapsed: started.elapsed(),
+                        });
+                    }
+                    Readiness::WrongService => {
+                        return Err(WaitError::WrongService(started.elapsed()))
+                    }
+                    Readiness::NotYet => {}
+                }
+            }
+            let waited = started.elapsed().as_secs();
+            if waited >= last_report + WAIT_LOG_INTERVAL_SECS {
+                last_report = waited;
+                let phase = if port_reported { "waiting for health" } else { "waiting for port" };
+                println!("[PhotoSense] Waiting for backend... ({}s, {})", waited, phase);
+                on_progress(progress(if port_reported {
+                    "waiting_for_health"
+                } else {
+                    "waiting_for_port"
+                }));
+            }
+            let jitter = interval.mul_f64(rand::random::<f64>()) / POLL_JITTER_DIVISOR;
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            thread::sleep((interval + jitter).min(remaining));
+            interval = (interval * 2).min(self.max_interval);
+        }
+    }
+}
+
+pub fn track_pid(pid: Option<u32>) {
+    BACKEND_PID.store(pid.unwrap_or(0), Ordering::SeqCst);
+}
+
+/// Asks the tracked backend to stop using only the PID atomic, so it is safe
+/// from signal and panic handlers. Async-signal-safe on Unix.
+#[cfg(unix)]
+pub fn kill_tracked_backend() {
+    SHUTTING_DOWN.store(true, Ordering::SeqCst);
+    let pid = BACKEND_PID.load(Ordering::SeqCst);
+    if pid != 0 {
+        // SAFETY: kill is async-signal-safe and takes no pointers.
+        unsafe {
+            libc::kill(pid as libc::pid_t, libc::SIGTERM);
+        }
+    }
+}
+
+/// Asks the tracked backend to stop using only the PID atomic, so it is safe
+/// from console and panic handlers.
+#[cfg(windows)]
+pub fn kill_tracked_backend() {
+    use std::os::windows::process::CommandExt;
+
+    SHUTTING_DOWN.store(true, Ordering::SeqCst);
+    let pid = BACKEND_PID.load(Ordering::SeqCst);
+    if pid != 0 {
+        let _ = std::process::Command::new("taskkill")
+            .args(["/F", "/T", "/PID", &pid.to_string()])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output();
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_terminate(signal: libc::c_int) {
+    kill_tracked_backend();
+    // SAFETY: signal and raise are async-signal-safe. Restoring the default
+    // disposition and re-raising lets the process die with the original
+    // signal once the backend has been told to stop.
+    unsafe {
+        libc::signal(signal, libc::SIG_DFL);
+        libc::raise(signal);
+    }
+}
+
+/// Makes sure a SIGTERM/SIGINT/SIGHUP sent to the shell (logout, `kill`,
+/// systemd stop) also stops the backend instead of orphaning it.
+#[cfg(unix)]
+pub fn install_signal_handlers() {
+    for signal in [libc::SIGTERM, libc::SIGINT, libc::SIGHUP] {
+        // SAFETY: installs a handler that only performs async-signal-safe work.
+        unsafe {
+            libc::signal(signal, on_terminate as *const () as libc::sighandler_t);
+        }
+    }
+}
+
+#[cfg(windows)]
+unsafe extern "system" fn on_console_event(_event: u32) -> windows_sys::Win32::Foundation::BOOL {
+    // Console handlers run on their own thread, so spawning is fine here.
+    kill_tracked_backend();
+    // Not handled: let the default handler terminate the process.
+    0
+}
+
+#[cfg(windows)]
+pub fn install_signal_handlers() {
+    use windows_sys::Win32::System::Console::SetConsoleCtrlHandler;
+
+    // SAFETY: registers a plain function pointer for the process lifetime.
+    unsafe {
+        SetConsoleCtrlHandler(Some(on_console_event), 1);
+    }
+}
+
+/// Runs a shutdown action at most once, however many exit paths (window
+/// close, tray quit, process exit) end up calling it.
+pub struct Cleanup {
+    done: AtomicBool,
+}
+
+impl Cleanup {
+    pub const fn new() -> Self {
+        Cleanup {
+            done: AtomicBool::new(false),
+        }
+    }
+
+    /// Calls `action` on the first invocation only. Returns whether it ran.
+    pub fn run_once(&self, action: impl FnOnce()) -> bool {
+        if self.done.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        action();
+        true
+    }
+}
+
+#[cfg(unix)]
+fn request_graceful_exit(child: &Child, _port: u16) -> bool {
+    // SAFETY: kill(2) with a PID we spawned ourselves; no memory is touched.
+    unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGTERM) == 0 }
+}
+
+#[cfg(windows)]
+fn request_graceful_exit(child: &Child, port: u16) -> bool {
+    use windows_sys::Win32::System::Console::{GenerateConsoleCtrlEvent, CTRL_BREAK_EVENT};
+
+    // The backend is spawned with CREATE_NEW_PROCESS_GROUP, so its PID is also
+    // its process group id. This only works when we share a console with it;
+    // otherwise fall back to asking over HTTP.
+    // SAFETY: plain Win32 call with integer arguments.
+    if unsafe { GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, child.id()) } != 0 {
+        return true;
+    }
+    http_request(port, "POST", "/shutdown", health_timeout())
+        .map(|resp| resp.status == 200)
+        .unwrap_or(false)
+}
+
+#[cfg(unix)]
+fn force_kill(child: &mut Child) {
+    let _ = child.kill();
+}
+
+#[cfg(windows)]
+fn force_kill(child: &mut Child) {
+    use std::os::windows::process::CommandExt;
+
+    // Kill the whole tree: PyInstaller bundles run the server in a child of
+    // the process we spawned.
+    let _ = std::process::Command::new("taskkill")
+        .args(["/F", "/T", "/PID", &child.id().to_string()])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output();
+    let _ = child.kill();
+}
+
+/// Asks the backend to exit on its own, then kills it if it is still running
+/// once `timeout` has elapsed.
+pub fn stop_child(child: &mut Child, port: u16, timeout: Duration) {
+    if request_graceful_exit(child, port) {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if matches!(child.try_wait(), Ok(Some(_))) && !is_port_open(port) {
+                println!("[PhotoSense] Backend exited gracefully");
+                return;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        eprintln!(
+            "[Warning] Backend did not exit within {}s, forcing shutdown",
+            timeout.as_secs()
+        );
+    }
+    force_kill(child);
+    let _ = child.wait();
+
+    // One-file bundles serve from a grandchild whose PID we never saw; if the
+    // port is still held by a PhotoSense backend, go after it by port.
+    thread::sleep(Duration::from_millis(PORT_RELEASE_WAIT_MS));
+    if is_port_open(port) && is_our_backend(port) {
+        let killed = kill_by_port(port);
+        eprintln!(
+            "[Warning] Backend still held port {} after exit, killed PIDs {:?}",
+            port, killed
+        );
+    }
+}
+
+/// PIDs listening on `port`, excluding ourselves.
+#[cfg(unix)]
+pub fn pids_on_port(port: u16) -> Vec<u32> {
+    let output = match std::process::Command::new("lsof")
+        .args(["-t", &format!("-iTCP:{}", port), "-sTCP:LISTEN"])
+        .output()
+    {
+        Ok(output) => output,
+        Err(_) => return Vec::new(),
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().parse().ok())
+        .filter(|pid| *pid != std::process::id())
+        .collect()
+}
+
+#[cfg(windows)]
+pub fn pids_on_port(port: u16) -> Vec<u32> {
+    use std::os::windows::process::CommandExt;
+
+    let output = match std::process::Command::new("netstat")
+        .args(["-ano", "-p", "TCP"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+    {
+        Ok(output) => output,
+        Err(_) => return Vec::new(),
+    };
+    // Lines look like: "  TCP    127.0.0.1:8000    0.0.0.0:0    LISTENING    1234".
+    // The state column is localized, so listeners are recognized by their
+    // empty remote address instead.
+    let suffix = format!(":{}", port);
+    let mut pids: Vec<u32> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                [_, local, remote, _, pid] if local.ends_with(&suffix) && remote.ends_with(":0") => {
+                    pid.parse().ok()
+                }
+                _ => None,
+            }
+        })
+        .filter(|pid| *pid != std::process::id())
+        .collect();
+    pids.sort_unstable();
+    pids.dedup();
+    pids
+}
+
+/// Kills whatever is listening on `port` and returns the PIDs it targeted.
+pub fn kill_by_port(port: u16) -> Vec<u32> {
+    let pids = pids_on_port(port);
+    for pid in &pids {
+        kill_pid(*pid);
+    }
+    pids
+}
+
+/// Force-kills `pid` (and on Windows its process tree).
+pub fn kill_pid(pid: u32) {
+    #[cfg(unix)]
+    // SAFETY: kill(2) with integer arguments; no memory is touched.
+    unsafe {
+        libc::kill(pid as libc::pid_t, libc::SIGKILL);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+
+        let _ = std::process::Command::new("taskkill")
+            .args(["/F", "/T", "/PID", &pid.to_string()])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output();
+    }
+}
+
+pub fn tracked_pid() -> Option<u32> {
+    Some(BACKEND_PID.load(Ordering::SeqCst)).filter(|pid| *pid != 0)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendProcess {
+    pub pid: u32,
+    pub parent_pid: u32,
+    pub name: String,
+}
+
+/// Linux truncates process names to this many bytes.
+const COMM_LEN: usize = 15;
+
+fn is_backend_name(name: &str, names: &[&str]) -> bool {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    names.iter().any(|wanted| {
+        base.eq_ignore_ascii_case(wanted)
+            || (cfg!(all(unix, not(target_os = "macos")))
+                && base.len() == COMM_LEN
+                && wanted.starts_with(base))
+    })
+}
+
+/// Running processes whose executable is one of `names`, excluding ourselves.
+#[cfg(unix)]
+pub fn find_backend_processes(names: &[&str]) -> Vec<BackendProcess> {
+    // `comm` goes last because on macOS it is the full path, spaces and all.
+    let output = match std::process::Command::new("ps")
+        .args(["-A", "-o", "pid=", "-o", "ppid=", "-o", "comm="])
+        .output()
+    {
+        Ok(output) => output,
+        Err(_) => return Vec::new(),
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?.parse().ok()?;
+            let parent_pid = fields.next()?.parse().ok()?;
+            let name = fields.collect::<Vec<_>>().join(" ");
+            Some(BackendProcess { pid, parent_pid, name })
+        })
+        .filter(|process| process.pid != std::process::id() && is_backend_name(&process.name, names))
+        .collect()
+}
+
+#[cfg(windows)]
+pub fn find_backend_processes(names: &[&str]) -> Vec<BackendProcess> {
+    use windows_sys::Win32::Foundation::{CloseHandle, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W,
+        TH32CS_SNAPPROCESS,
+    };
+
+    // SAFETY: plain Win32 call with integer arguments.
+    let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) };
+    if snapshot == INVALID_HANDLE_VALUE {
+        return Vec::new();
+    }
+    // SAFETY: PROCESSENTRY32W is plain data; zeroed is valid.
+    let mut entry: PROCESSENTRY32W = unsafe { std::mem::zeroed() };
+    entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
+    let mut processes = Vec::new();
+    // SAFETY: `snapshot` is valid and `entry` is a live, sized out-buffer.
+    let mut more = unsafe { Process32FirstW(snapshot, &mut entry) } != 0;
+    while more {
+        let len = entry.szExeFile.iter().position(|c| *c == 0).unwrap_or(entry.szExeFile.len());
+        let name = String::from_utf16_lossy(&entry.szExeFile[..len]);
+        if entry.th32ProcessID != std::process::id() && is_backend_name(&name, names) {
+            processes.push(BackendProcess {
+                pid: entry.th32ProcessID,
+                parent_pid: entry.th32ParentProcessID,
+                name,
+            });
+        }
+        // SAFETY: as above.
+        more = unsafe { Process32NextW(snapshot, &mut entry) } != 0;
+    }
+    // SAFETY: the snapshot handle is closed exactly once.
+    unsafe { CloseHandle(snapshot) };
+    processes
+}
+
+#[cfg(unix)]
+const LOW_PRIORITY_NICE: libc::c_int = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    Normal,
+}
+
+impl Priority {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "low" => Some(Priority::Low),
+            "normal" => Some(Priority::Normal),
+            _ => None,
+        }
+    }
+}
+
+/// Inference device the backend should use. `Auto` and `Gpu` both use an
+/// accelerator when the backend finds one; `Cpu` forces CPU inference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Device {
+    Auto,
+    Gpu,
+    Cpu,
+}
+
+impl Device {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "auto" => Some(Device::Auto),
+            "gpu" => Some(Device::Gpu),
+            "cpu" => Some(Device::Cpu),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Device::Auto => "auto",
+            Device::Gpu => "gpu",
+            Device::Cpu => "cpu",
+        }
+    }
+}
+
+/// Device from `PHOTOSENSE_DEVICE`, defaulting to `Auto`.
+pub fn device_from_env() -> Device {
+    match std::env::var(DEVICE_ENV) {
+        Ok(value) => Device::parse(&value).unwrap_or_else(|| {
+            eprintln!("[Error] Invalid {} value '{}', using auto", DEVICE_ENV, value);
+            Device::Auto
+        }),
+        Err(_) => Device::Auto,
+    }
+}
+
+/// Sets the scheduling priority of the backend process `pid`. Raising it
+/// back to normal after lowering it may need privileges on Unix.
+#[cfg(unix)]
+pub fn set_process_priority(pid: u32, priority: Priority) -> Result<(), String> {
+    let nice = match priority {
+        Priority::Low => LOW_PRIORITY_NICE,
+        Priority::Normal => 0,
+    };
+    // Linux applies nice values per thread, so every existing thread of the
+    // backend has to be adjusted, not just its main one.
+    #[cfg(target_os = "linux")]
+    let targets: Vec<u32> = std::fs::read_dir(format!("/proc/{}/task", pid))
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .filter_map(|e| e.file_name().to_string_lossy().parse().ok())
+                .collect()
+        })
+        .unwrap_or_else(|_| vec![pid]);
+    #[cfg(not(target_os = "linux"))]
+    let targets = vec![pid];
+
+    for target in targets {
+        // SAFETY: setpriority(2) with integer arguments; no memory is touched.
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, target as libc::id_t, nice) } != 0 {
+            return Err(format!(
+                "Failed to set backend priority: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn set_process_priority(pid: u32, priority: Priority) -> Result<(), String> {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, SetPriorityClass, BELOW_NORMAL_PRIORITY_CLASS, NORMAL_PRIORITY_CLASS,
+        PROCESS_SET_INFORMATION,
+    };
+
+    let class = match priority {
+        Priority::Low => BELOW_NORMAL_PRIORITY_CLASS,
+        Priority::Normal => NORMAL_PRIORITY_CLASS,
+    };
+    // SAFETY: plain Win32 calls with integer arguments; the handle is closed
+    // before returning.
+    unsafe {
+        let handle = OpenProcess(PROCESS_SET_INFORMATION, 0, pid);
+        if handle == 0 {
+            return Err(format!("Failed to open backend process {}", pid));
+        }
+        let ok = SetPriorityClass(handle, class);
+        CloseHandle(handle);
+        if ok == 0 {
+            return Err("Failed to set backend priority".to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    /// Answers every request on a local port with `status` and `body`.
+    fn serve(status: u16, body: &'static str) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut reader = BufReader::new(&stream);
+                let mut line = String::new();
+                let mut request = false;
+                // Port probes hang up without sending anything.
+                while reader.read_line(&mut line).unwrap_or(0) > 2 {
+                    request = true;
+                    line.clear();
+                }
+                if request {
+                    let _ = write!(
+                        stream,
+                        "HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                }
+            }
+        });
+        port
+    }
+
+    fn waiter(deadline: Duration) -> BackendWaiter {
+        BackendWaiter {
+            connect_timeout: Duration::from_millis(100),
+            health_timeout: Duration::from_secs(1),
+            deadline,
+            min_interval: Duration::from_millis(10),
+            max_interval: Duration::from_millis(50),
+        }
+    }
+
+    #[test]
+    fn cleanup_runs_its_action_once() {
+        let cleanup = Cleanup::new();
+        let kills = AtomicUsize::new(0);
+        let kill = || {
+            kills.fetch_add(1, Ordering::SeqCst);
+        };
+        assert!(cleanup.run_once(kill));
+        assert!(!cleanup.run_once(kill));
+        assert_eq!(kills.load(Ordering::SeqCst), 1);
+    }
+    #[test]
+    fn host_validation_rejects_garbage() {
+        assert!(is_valid_host("127.0.0.1"));
+        assert!(is_valid_host("::1"));
+        assert!(is_valid_host("photos.example-host.local"));
+        assert!(!is_valid_host(""));
+        // backend_url adds the brackets itself.
+        assert!(!is_valid_host("[::1]"));
+        assert!(!is_valid_host("localhost:8000"));
+        assert!(!is_valid_host("a b"));
+        assert!(!is_valid_host(&format!("{}.example", "a".repeat(64))));
+        assert!(!is_valid_host("-leading.example"));
+    }
+
+    #[test]
+    fn garbage_hosts_resolve_to_nothing() {
+        for host in ["", "localhost:8000", "a b", "[::1]"] {
+            assert!(resolve_host(host).is_empty(), "{host:?} resolved");
+        }
+        let ip: IpAddr = "192.0.2.7".parse().unwrap();
+        assert_eq!(resolve_host("192.0.2.7"), vec![ip]);
+        assert!(resolve_host("::1").contains(&IpAddr::V6(Ipv6Addr::LOCALHOST)));
+    }
+
+    #[test]
+    fn port_probe_follows_the_listener() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(is_port_open(port));
+        drop(listener);
+        assert!(!is_port_open(port));
+    }
+
+    #[test]
+    fn waiter_gives_up_at_the_deadline_on_a_closed_port() {
+        let port = find_free_port().unwrap();
+        let deadline = Duration::from_millis(300);
+        match waiter(deadline).wait(port, |_| {}) {
+            Err(WaitError::PortNeverOpened(elapsed)) => {
+                assert!(elapsed >= deadline && elapsed < deadline * 4, "{elapsed:?}")
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn waiter_reports_each_phase_once_in_order() {
+        let port = serve(200, r#"{"service":"photosense","status":"healthy"}"#);
+        let mut phases = Vec::new();
+        let ready = waiter(Duration::from_secs(5)).wait(port, |p| phases.push(p.phase));
+        assert!(ready.is_ok(), "{ready:?}");
+        assert_eq!(phases, ["port_open", "health_ok"]);
+    }
+
+    #[test]
+    fn waiter_rejects_a_service_that_is_not_photosense() {
+        for (status, body) in [(404, "Not Found"), (200, r#"{"status":"ok"}"#)] {
+            let port = serve(status, body);
+            let outcome = waiter(Duration::from_secs(5)).wait(port, |_| {});
+            assert!(matches!(outcome, Err(WaitError::WrongService(_))), "{status}: {outcome:?}");
+        }
+    }
+}
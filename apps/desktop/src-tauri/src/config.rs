@@ -0,0 +1,136 @@
+//! Optional `photosense.toml` in the data dir. Each key maps onto one of the
+//! `PHOTOSENSE_*` environment variables read by the lifecycle code; values
+//! from the file are only applied when that variable is not already set, so
+//! the environment always wins.
+
+use crate::backend::{
+    auto_start, backend_host, backend_port, device_from_env, focus_on_ready, max_restart_attempts,
+    proxy_concurrency, sanitize_env_enabled, startup_grace, startup_timeout, status_title_enabled,
+    AUTO_START_ENV, BACKEND_HOST_ENV, BACKEND_PORT_ENV, DEVICE_ENV, FOCUS_ON_READY_ENV,
+    MAX_RESTARTS_ENV, PROXY_CONCURRENCY_ENV, SANITIZE_ENV_ENV, STARTUP_GRACE_ENV,
+    STARTUP_TIMEOUT_ENV, STATUS_TITLE_ENV,
+};
+use crate::http_pool::{self, POOL_IDLE_SECS_ENV, POOL_MAX_IDLE_ENV};
+use crate::logs::{self, LOG_LEVEL_ENV};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+pub const CONFIG_FILE: &str = "photosense.toml";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    pub port: Option<u16>,
+    pub host: Option<String>,
+    pub startup_timeout_secs: Option<u64>,
+    pub startup_grace_secs: Option<u64>,
+    pub auto_restart: Option<bool>,
+    pub auto_start: Option<bool>,
+    pub status_title: Option<bool>,
+    pub focus_on_ready: Option<bool>,
+    pub sanitize_env: Option<bool>,
+    pub http_pool_max_idle: Option<usize>,
+    pub http_pool_idle_secs: Option<u64>,
+    pub proxy_concurrency: Option<usize>,
+    pub device: Option<String>,
+    pub log_level: Option<String>,
+}
+
+/// Settings in effect after merging the file with the environment.
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectiveConfig {
+    pub port: u16,
+    pub host: String,
+    pub startup_timeout_secs: u64,
+    pub startup_grace_secs: u64,
+    pub auto_restart: bool,
+    pub auto_start: bool,
+    pub status_title: bool,
+    pub focus_on_ready: bool,
+    pub sanitize_env: bool,
+    pub http_pool_max_idle: usize,
+    pub http_pool_idle_secs: u64,
+    pub proxy_concurrency: usize,
+    pub device: String,
+    pub log_level: String,
+    pub source: Option<String>,
+}
+
+/// Reads `photosense.toml` from `data_dir`. A missing file is not an error.
+pub fn load(data_dir: &Path) -> Result<Option<FileConfig>, String> {
+    let path = data_dir.join(CONFIG_FILE);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {}: {e}", path.display())),
+    };
+    toml::from_str(&text)
+        .map(Some)
+        .map_err(|e| format!("Invalid {}: {e}", path.display()))
+}
+
+/// Exports file values as environment variables unless already set. Must
+/// run at startup, before other threads exist and before any setting is read.
+pub fn apply_to_env(config: &FileConfig) {
+    let set_default = |name: &str, value: Option<String>| {
+        if let Some(value) = value {
+            if std::env::var_os(name).is_none() {
+                std::env::set_var(name, value);
+            }
+        }
+    };
+    set_default(BACKEND_PORT_ENV, config.port.map(|p| p.to_string()));
+    set_default(BACKEND_HOST_ENV, config.host.clone());
+    set_default(STARTUP_TIMEOUT_ENV, config.startup_timeout_secs.map(|s| s.to_string()));
+    set_default(STARTUP_GRACE_ENV, config.startup_grace_secs.map(|s| s.to_string()));
+    set_default(
+        MAX_RESTARTS_ENV,
+        config.auto_restart.filter(|enabled| !enabled).map(|_| "0".to_string()),
+    );
+    set_default(AUTO_START_ENV, config.auto_start.map(|enabled| enabled.to_string()));
+    set_default(STATUS_TITLE_ENV, config.status_title.map(|enabled| enabled.to_string()));
+    set_default(FOCUS_ON_READY_ENV, config.focus_on_ready.map(|enabled| enabled.to_string()));
+    set_default(SANITIZE_ENV_ENV, config.sanitize_env.map(|enabled| enabled.to_string()));
+    set_default(POOL_MAX_IDLE_ENV, config.http_pool_max_idle.map(|n| n.to_string()));
+    set_default(POOL_IDLE_SECS_ENV, config.http_pool_idle_secs.map(|s| s.to_string()));
+    set_default(PROXY_CONCURRENCY_ENV, config.proxy_concurrency.map(|n| n.to_string()));
+    set_default(DEVICE_ENV, config.device.clone());
+    set_default(LOG_LEVEL_ENV, config.log_level.clone());
+}
+
+pub fn effective(source: Option<&Path>) -> EffectiveConfig {
+    EffectiveConfig {
+        port: backend_port(),
+        host: backend_host().to_string(),
+        startup_timeout_secs: startup_timeout().as_secs(),
+        startup_grace_secs: startup_grace().as_secs(),
+        auto_restart: max_restart_attempts() > 0,
+        auto_start: auto_start(),
+        status_title: status_title_enabled(),
+        focus_on_ready: focus_on_ready(),
+        sanitize_env: sanitize_env_enabled(),
+        http_pool_max_idle: http_pool::max_idle(),
+        http_pool_idle_secs: http_pool::idle_timeout().as_secs(),
+        proxy_concurrency: proxy_concurrency(),
+        device: device_from_env().as_str().to_string(),
+        log_level: logs::forward_level().as_str().to_string(),
+        source: source.map(|p| p.to_string_lossy().to_string()),
+    }
+}
+
+/// Sets one key in `photosense.toml`, creating the file if needed. The file
+/// is rewritten, so comments in it are lost.
+pub fn save_value(data_dir: &Path, key: &str, value: toml::Value) -> Result<(), String> {
+    let path = data_dir.join(CONFIG_FILE);
+    let mut table = match fs::read_to_string(&path) {
+        Ok(text) => toml::from_str::<toml::Table>(&text)
+            .map_err(|e| format!("Invalid {}: {e}", path.display()))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml::Table::new(),
+        Err(e) => return Err(format!("Failed to read {}: {e}", path.display())),
+    };
+    table.insert(key.to_string(), value);
+    let text = toml::to_string(&table).map_err(|e| e.to_string())?;
+    fs::create_dir_all(data_dir).map_err(|e| format!("Failed to create data dir: {e}"))?;
+    fs::write(&path, text).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
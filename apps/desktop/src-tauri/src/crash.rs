@@ -0,0 +1,103 @@
+//! One-file crash reports written when the backend exits with a failure, so
+//! users have a single artifact to attach to bug reports.
+
+use crate::logs;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const REPORT_PREFIX: &str = "crash-report-";
+const REPORT_LOG_LINES: usize = 200;
+const MAX_REPORTS: usize = 10;
+
+/// Writes `crash-report-<unix-ms>.txt` into `dir` and prunes old reports.
+pub fn write_report(
+    dir: &Path,
+    backend_log: &Path,
+    exit_code: Option<i32>,
+    app_version: &str,
+) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let path = dir.join(format!("{}{}.txt", REPORT_PREFIX, timestamp));
+
+    let tail = logs::read_last_lines(backend_log, REPORT_LOG_LINES).unwrap_or_default();
+    let mut file = fs::File::create(&path)?;
+    writeln!(file, "PhotoSense-AI crash report")?;
+    writeln!(file, "App version: {}", app_version)?;
+    writeln!(
+        file,
+        "OS: {} {} ({})",
+        std::env::consts::OS,
+        os_version(),
+        std::env::consts::ARCH
+    )?;
+    match exit_code {
+        Some(code) => writeln!(file, "Exit code: {}", code)?,
+        None => writeln!(file, "Exit code: none (killed by signal)")?,
+    }
+    writeln!(file, "Timestamp (unix ms): {}", timestamp)?;
+    writeln!(file)?;
+    writeln!(file, "--- Last {} lines of backend.log ---", tail.len())?;
+    for line in &tail {
+        writeln!(file, "{}", line)?;
+    }
+
+    prune(dir, MAX_REPORTS);
+    Ok(path)
+}
+
+/// Deletes the oldest reports so at most `keep` remain. Report names embed a
+/// millisecond timestamp, so lexical order is chronological.
+fn prune(dir: &Path, keep: usize) {
+    let mut reports: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .filter(|e| e.file_name().to_string_lossy().starts_with(REPORT_PREFIX))
+            .map(|e| e.path())
+            .collect(),
+        Err(_) => return,
+    };
+    reports.sort();
+    let excess = reports.len().saturating_sub(keep);
+    for old in &reports[..excess] {
+        let _ = fs::remove_file(old);
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn os_version() -> String {
+    fs::read_to_string("/proc/sys/kernel/osrelease")
+        .map(|v| v.trim().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(target_os = "macos")]
+pub fn os_version() -> String {
+    std::process::Command::new("sw_vers")
+        .arg("-productVersion")
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(windows)]
+pub fn os_version() -> String {
+    use std::os::windows::process::CommandExt;
+
+    std::process::Command::new("cmd")
+        .args(["/C", "ver"])
+        .creation_flags(crate::backend::CREATE_NO_WINDOW)
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn os_version() -> String {
+    String::new()
+}
@@ -0,0 +1,107 @@
+//! Where the shell keeps its data, and carrying data over from the location
+//! the backend used before the shell chose one for it.
+
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const DATA_DIR_ENV: &str = "PHOTOSENSE_DATA_DIR";
+pub const MIGRATED_MARKER: &str = ".migrated";
+/// The standalone backend's own default, under the platform data dir.
+const LEGACY_DIR_NAME: &str = "PhotoSense-AI";
+/// Only a directory holding a library is worth migrating.
+const LIBRARY_FILE: &str = "photosense.db";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Migration {
+    pub from: String,
+    pub to: String,
+    pub files: u64,
+    pub bytes: u64,
+    pub skipped: u64,
+}
+
+/// The data dir for this platform: `PHOTOSENSE_DATA_DIR` if set, otherwise
+/// the app data dir for the bundle identifier.
+pub fn resolve(config: &tauri::Config) -> Option<PathBuf> {
+    match std::env::var_os(DATA_DIR_ENV).filter(|dir| !dir.is_empty()) {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => tauri::api::path::app_data_dir(config),
+    }
+}
+
+pub fn legacy_dir() -> Option<PathBuf> {
+    tauri::api::path::data_dir().map(|dir| dir.join(LEGACY_DIR_NAME))
+}
+
+/// Copies a library from the legacy location into `data_dir` if `data_dir`
+/// has none yet, then writes `MIGRATED_MARKER` so this runs at most once.
+/// The legacy copy is left in place, and files already present in
+/// `data_dir` are not overwritten.
+pub fn migrate(data_dir: &Path) -> Result<Option<Migration>, String> {
+    if data_dir.join(MIGRATED_MARKER).exists() || data_dir.join(LIBRARY_FILE).exists() {
+        return Ok(None);
+    }
+    let legacy = match legacy_dir() {
+        Some(legacy) if legacy.join(LIBRARY_FILE).is_file() => legacy,
+        _ => return Ok(None),
+    };
+    if let (Ok(a), Ok(b)) = (fs::canonicalize(&legacy), fs::canonicalize(data_dir)) {
+        if a == b {
+            return Ok(None);
+        }
+    }
+
+    let mut migration = Migration {
+        from: legacy.to_string_lossy().to_string(),
+        to: data_dir.to_string_lossy().to_string(),
+        files: 0,
+        bytes: 0,
+        skipped: 0,
+    };
+    // The library goes last: its presence is what marks a data dir as in use,
+    // so an interrupted copy is picked up again on the next launch.
+    copy_dir(&legacy, data_dir, &[LIBRARY_FILE], &mut migration)
+        .and_then(|_| {
+            migration.bytes += copy_file(&legacy.join(LIBRARY_FILE), &data_dir.join(LIBRARY_FILE))?;
+            migration.files += 1;
+            Ok(())
+        })
+        .map_err(|e| format!("Failed to migrate {}: {e}", legacy.display()))?;
+    fs::write(data_dir.join(MIGRATED_MARKER), &migration.from)
+        .map_err(|e| format!("Failed to write migration marker: {e}"))?;
+    Ok(Some(migration))
+}
+
+fn copy_dir(from: &Path, to: &Path, skip: &[&str], migration: &mut Migration) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        if skip.iter().any(|name| entry.file_name() == *name) {
+            continue;
+        }
+        let target = to.join(entry.file_name());
+        // Symlinks are skipped rather than followed out of the legacy dir.
+        let meta = fs::symlink_metadata(entry.path())?;
+        if meta.is_dir() {
+            copy_dir(&entry.path(), &target, &[], migration)?;
+        } else if !meta.is_file() || target.exists() {
+            migration.skipped += 1;
+        } else {
+            migration.bytes += copy_file(&entry.path(), &target)?;
+            migration.files += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Copies through a temporary name so an interrupted copy never leaves a
+/// truncated file that a later run would mistake for a finished one.
+fn copy_file(from: &Path, to: &Path) -> io::Result<u64> {
+    let mut partial = to.as_os_str().to_owned();
+    partial.push(".partial");
+    let bytes = fs::copy(from, &partial)?;
+    fs::rename(&partial, to)?;
+    Ok(bytes)
+}
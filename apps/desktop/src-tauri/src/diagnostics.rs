@@ -0,0 +1,256 @@
+//! Single-archive diagnostics bundle for support requests: backend logs,
+//! startup and crash reports, the config file, local metrics and whatever
+//! extra text the caller wants to include (effective config, system info).
+
+use crate::config::CONFIG_FILE;
+use crate::crash::REPORT_PREFIX;
+use crate::metrics::METRICS_FILE;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const BUNDLE_DIR: &str = "diagnostics";
+const BUNDLE_PREFIX: &str = "photosense-diagnostics-";
+
+/// Writes `diagnostics/photosense-diagnostics-<unix-ms>.zip` under
+/// `data_dir` and returns its path. `log_file` and its rotations go under
+/// `logs/`; `extra` entries are `(name, contents)` pairs stored at the
+/// archive root. Missing files are skipped.
+pub fn write_bundle(
+    data_dir: &Path,
+    log_file: &Path,
+    extra: &[(&str, String)],
+) -> Result<PathBuf, String> {
+    let out_dir = data_dir.join(BUNDLE_DIR);
+    fs::create_dir_all(&out_dir)
+        .map_err(|e| format!("Failed to create diagnostics directory: {e}"))?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let path = out_dir.join(format!("{}{}.zip", BUNDLE_PREFIX, timestamp));
+    let file =
+        fs::File::create(&path).map_err(|e| format!("Failed to create {}: {e}", path.display()))?;
+
+    let modified = dos_datetime(timestamp as u64 / 1000);
+    let mut archive = ZipWriter::new(io::BufWriter::new(file), modified);
+    let mut add = |name: &str, data: &[u8]| {
+        archive
+            .add(name, data)
+            .map_err(|e| format!("Failed to add {} to diagnostics: {e}", name))
+    };
+
+    for (name, contents) in extra {
+        add(name, contents.as_bytes())?;
+    }
+    for name in ["startup-report.json", CONFIG_FILE, METRICS_FILE] {
+        if let Ok(data) = fs::read(data_dir.join(name)) {
+            add(name, &data)?;
+        }
+    }
+    // Files are read whole rather than streamed because backend.log may grow
+    // while it is being archived, which would break the entry's size and CRC.
+    let log_dir = log_file.parent().unwrap_or(data_dir);
+    let log_name = log_file.file_name().unwrap_or_default().to_string_lossy();
+    for (dir, prefix, archive_dir) in [
+        (log_dir, log_name.as_ref(), "logs/"),
+        (data_dir, REPORT_PREFIX, "crash-reports/"),
+    ] {
+        for file in files_with_prefix(dir, prefix) {
+            if let Ok(data) = fs::read(&file) {
+                let name = file.file_name().unwrap_or_default().to_string_lossy();
+                add(&format!("{}{}", archive_dir, name), &data)?;
+            }
+        }
+    }
+
+    archive
+        .finish()
+        .map_err(|e| format!("Failed to write diagnostics bundle: {e}"))?;
+    Ok(path)
+}
+
+fn files_with_prefix(dir: &Path, prefix: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .filter(|e| e.file_name().to_string_lossy().starts_with(prefix))
+            .map(|e| e.path())
+            .filter(|p| p.is_file())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    files.sort();
+    files
+}
+
+struct ZipEntry {
+    name: String,
+    crc: u32,
+    compressed: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Just enough of the zip format for the bundle: deflated entries, UTF-8
+/// names and one timestamp, without zip64, so each entry and the archive
+/// stay under 4 GiB.
+struct ZipWriter<W: Write> {
+    out: W,
+    written: u64,
+    modified: (u16, u16),
+    entries: Vec<ZipEntry>,
+}
+
+const ZIP_VERSION: u16 = 20;
+const ZIP_UTF8_NAMES: u16 = 1 << 11;
+const ZIP_DEFLATE: u16 = 8;
+
+impl<W: Write> ZipWriter<W> {
+    fn new(out: W, modified: (u16, u16)) -> Self {
+        ZipWriter {
+            out,
+            written: 0,
+            modified,
+            entries: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+        let entry = ZipEntry {
+            name: name.to_string(),
+            crc: crc32fast::hash(data),
+            compressed: fits_u32(compressed.len() as u64)?,
+            size: fits_u32(data.len() as u64)?,
+            offset: fits_u32(self.written)?,
+        };
+
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        header.extend_from_slice(&ZIP_VERSION.to_le_bytes());
+        self.push_entry_fields(&mut header, &entry);
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(name.as_bytes());
+        self.write(&header)?;
+        self.write(&compressed)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Writes the central directory and returns the underlying writer.
+    fn finish(mut self) -> io::Result<W> {
+        let start = fits_u32(self.written)?;
+        let entries = std::mem::take(&mut self.entries);
+        for entry in &entries {
+            let mut record = Vec::with_capacity(46 + entry.name.len());
+            record.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            record.extend_from_slice(&ZIP_VERSION.to_le_bytes());
+            record.extend_from_slice(&ZIP_VERSION.to_le_bytes());
+            self.push_entry_fields(&mut record, entry);
+            // Extra field, comment, disk number, internal and external
+            // attributes, then where the local header starts.
+            record.extend_from_slice(&[0; 12]);
+            record.extend_from_slice(&entry.offset.to_le_bytes());
+            record.extend_from_slice(entry.name.as_bytes());
+            self.write(&record)?;
+        }
+        let size = fits_u32(self.written)? - start;
+        let count = u16::try_from(entries.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many entries"))?;
+
+        let mut end = Vec::with_capacity(22);
+        end.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        end.extend_from_slice(&[0; 4]);
+        end.extend_from_slice(&count.to_le_bytes());
+        end.extend_from_slice(&count.to_le_bytes());
+        end.extend_from_slice(&size.to_le_bytes());
+        end.extend_from_slice(&start.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());
+        self.write(&end)?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    /// The fields local headers and central records share, from the flags
+    /// through the name length.
+    fn push_entry_fields(&self, buf: &mut Vec<u8>, entry: &ZipEntry) {
+        let (time, date) = self.modified;
+        for field in [ZIP_UTF8_NAMES, ZIP_DEFLATE, time, date] {
+            buf.extend_from_slice(&field.to_le_bytes());
+        }
+        buf.extend_from_slice(&entry.crc.to_le_bytes());
+        buf.extend_from_slice(&entry.compressed.to_le_bytes());
+        buf.extend_from_slice(&entry.size.to_le_bytes());
+        buf.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.out.write_all(data)?;
+        self.written += data.len() as u64;
+        Ok(())
+    }
+}
+
+fn fits_u32(n: u64) -> io::Result<u32> {
+    u32::try_from(n).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "archive too large"))
+}
+
+/// MS-DOS `(time, date)` for a Unix timestamp, in UTC; zip has no time zone
+/// and can't go before 1980.
+fn dos_datetime(unix_secs: u64) -> (u16, u16) {
+    let days = (unix_secs / 86_400) as i64;
+    let secs = unix_secs % 86_400;
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    if year < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    let time = ((secs / 3600) << 11) | ((secs % 3600 / 60) << 5) | ((secs % 60) / 2);
+    let date = ((year - 1980).min(127) << 9) | (month << 5) | day;
+    (time as u16, date as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dos_datetime_matches_the_calendar() {
+        // 2024-02-29 13:45:30 UTC.
+        let (time, date) = dos_datetime(1_709_214_330);
+        assert_eq!((time >> 11, (time >> 5) & 0x3f, (time & 0x1f) * 2), (13, 45, 30));
+        assert_eq!((1980 + (date >> 9), (date >> 5) & 0xf, date & 0x1f), (2024, 2, 29));
+        assert_eq!(dos_datetime(0), (0, (1 << 5) | 1));
+    }
+
+    #[test]
+    fn archive_ends_with_a_directory_of_every_entry() {
+        let mut zip = ZipWriter::new(Vec::new(), dos_datetime(1_709_214_330));
+        zip.add("system-info.txt", b"hello hello hello").unwrap();
+        zip.add("logs/backend.log", b"").unwrap();
+        let bytes = zip.finish().unwrap();
+
+        assert_eq!(bytes[..4], 0x0403_4b50u32.to_le_bytes());
+        let end = &bytes[bytes.len() - 22..];
+        assert_eq!(end[..4], 0x0605_4b50u32.to_le_bytes());
+        assert_eq!(u16::from_le_bytes([end[10], end[11]]), 2);
+        let start = u32::from_le_bytes([end[16], end[17], end[18], end[19]]) as usize;
+        let size = u32::from_le_bytes([end[12], end[13], end[14], end[15]]) as usize;
+        assert_eq!(start + size, bytes.len() - 22);
+        assert_eq!(bytes[start..start + 4], 0x0201_4b50u32.to_le_bytes());
+    }
+}
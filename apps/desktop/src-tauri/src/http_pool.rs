@@ -0,0 +1,253 @@
+//! Keep-alive connection reuse for the shell's HTTP calls to the backend.
+//! The watchdog, status refresher and tray all poll, and without reuse each
+//! poll is a fresh connection. Idle connections are kept per port for a few
+//! seconds, below uvicorn's own 5s keep-alive limit so the server rarely
+//! closes one under us; `PHOTOSENSE_HTTP_POOL_MAX_IDLE=0` turns reuse off.
+
+use crate::transport::BackendStream;
+use std::io::{self, BufRead, BufReader, Read};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const POOL_MAX_IDLE_ENV: &str = "PHOTOSENSE_HTTP_POOL_MAX_IDLE";
+pub const POOL_IDLE_SECS_ENV: &str = "PHOTOSENSE_HTTP_POOL_IDLE_SECS";
+const DEFAULT_MAX_IDLE: usize = 4;
+const DEFAULT_IDLE_SECS: u64 = 4;
+const MAX_IDLE_LIMIT: usize = 32;
+const MAX_HEADER_BYTES: usize = 64 * 1024;
+
+struct Idle {
+    port: u16,
+    stream: BackendStream,
+    since: Instant,
+}
+
+static IDLE: Mutex<Vec<Idle>> = Mutex::new(Vec::new());
+
+/// Idle connections kept per port, capped at `MAX_IDLE_LIMIT`.
+pub fn max_idle() -> usize {
+    std::env::var(POOL_MAX_IDLE_ENV)
+        .ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .map(|n| n.min(MAX_IDLE_LIMIT))
+        .unwrap_or(DEFAULT_MAX_IDLE)
+}
+
+pub fn idle_timeout() -> Duration {
+    let secs = std::env::var(POOL_IDLE_SECS_ENV)
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(DEFAULT_IDLE_SECS);
+    Duration::from_secs(secs)
+}
+
+pub fn enabled() -> bool {
+    max_idle() > 0
+}
+
+/// Takes the most recently used idle connection to `port`, dropping any
+/// that have been idle too long.
+pub fn checkout(port: u16) -> Option<BackendStream> {
+    let timeout = idle_timeout();
+    let mut idle = IDLE.lock().unwrap();
+    idle.retain(|conn| conn.since.elapsed() < timeout);
+    let index = idle.iter().rposition(|conn| conn.port == port)?;
+    Some(idle.remove(index).stream)
+}
+
+pub fn checkin(port: u16, stream: BackendStream) {
+    let max = max_idle();
+    let mut idle = IDLE.lock().unwrap();
+    if idle.iter().filter(|conn| conn.port == port).count() >= max {
+        return;
+    }
+    idle.push(Idle {
+        port,
+        stream,
+        since: Instant::now(),
+    });
+}
+
+/// Drops every idle connection, e.g. once the backend they point at is gone.
+pub fn clear() {
+    IDLE.lock().unwrap().clear();
+}
+
+pub struct RawResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+    /// Whether the connection is positioned at the next response and may
+    /// be reused.
+    pub reusable: bool,
+}
+
+/// Reads one HTTP/1.1 response, delimited by `Content-Length` or chunked
+/// encoding, or by the connection closing when it has neither. A connection
+/// that closes before sending anything fails with `UnexpectedEof`, which on
+/// a reused connection means it went stale while idle.
+pub fn read_response(stream: &mut impl Read, max_body: u64) -> io::Result<RawResponse> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let status = line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| malformed("status line"))?;
+
+    let mut content_length = None;
+    let mut chunked = false;
+    let mut close = false;
+    let mut header_bytes = 0;
+    loop {
+        line.clear();
+        header_bytes += reader.read_line(&mut line)?;
+        if header_bytes > MAX_HEADER_BYTES {
+            return Err(malformed("headers too large"));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = Some(value.parse::<u64>().map_err(|_| malformed("length"))?);
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = value.to_ascii_lowercase().contains("chunked");
+        } else if name.eq_ignore_ascii_case("connection") {
+            close = value.eq_ignore_ascii_case("close");
+        }
+    }
+
+    let mut body = Vec::new();
+    let delimited = if chunked {
+        read_chunked(&mut reader, &mut body, max_body)?;
+        true
+    } else if let Some(length) = content_length {
+        if length > max_body {
+            return Err(too_large());
+        }
+        (&mut reader).take(length).read_to_end(&mut body)?;
+        if (body.len() as u64) < length {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        true
+    } else if status == 204 || status == 304 || (100..200).contains(&status) {
+        true
+    } else {
+        (&mut reader).take(max_body + 1).read_to_end(&mut body)?;
+        false
+    };
+    if body.len() as u64 > max_body {
+        return Err(too_large());
+    }
+    Ok(RawResponse {
+        status,
+        body,
+        reusable: delimited && !close,
+    })
+}
+
+fn read_chunked(reader: &mut impl BufRead, body: &mut Vec<u8>, max_body: u64) -> io::Result<()> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        let size = line.split(';').next().unwrap_or("").trim();
+        let size = u64::from_str_radix(size, 16).map_err(|_| malformed("chunk size"))?;
+        if size == 0 {
+            // Skip any trailers up to the blank line that ends the body.
+            loop {
+                line.clear();
+                if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+                    return Ok(());
+                }
+            }
+        }
+        if body.len() as u64 + size > max_body {
+            return Err(too_large());
+        }
+        let start = body.len();
+        (&mut *reader).take(size).read_to_end(body)?;
+        if ((body.len() - start) as u64) < size {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        line.clear();
+        reader.read_line(&mut line)?;
+    }
+}
+
+fn malformed(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("malformed HTTP response ({what})"),
+    )
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "response too large")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(raw: &str, max_body: u64) -> io::Result<RawResponse> {
+        read_response(&mut raw.as_bytes(), max_body)
+    }
+
+    #[test]
+    fn content_length_delimits_a_reusable_body() {
+        let resp = read("HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello", 64).unwrap();
+        assert_eq!(resp.status, 200);
+        assert_eq!(resp.body, b"hello");
+        assert!(resp.reusable);
+    }
+
+    #[test]
+    fn chunked_body_skips_extensions_and_trailers() {
+        let raw = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+                   5\r\nhello\r\n6;ext=1\r\n world\r\n0\r\nX-Trailer: yes\r\n\r\n";
+        let resp = read(raw, 64).unwrap();
+        assert_eq!(resp.body, b"hello world");
+        assert!(resp.reusable);
+    }
+
+    #[test]
+    fn truncated_bodies_are_unexpected_eof() {
+        let short = read("HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nhello", 64);
+        assert_eq!(short.err().unwrap().kind(), io::ErrorKind::UnexpectedEof);
+        let chunk = read("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\na\r\nhello", 64);
+        assert_eq!(chunk.err().unwrap().kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(read("", 64).err().unwrap().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn oversize_bodies_are_rejected() {
+        let length = read("HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n", 10);
+        assert_eq!(length.err().unwrap().kind(), io::ErrorKind::InvalidData);
+        let chunked = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+                       8\r\n01234567\r\n8\r\n89abcdef\r\n0\r\n\r\n";
+        assert_eq!(read(chunked, 10).err().unwrap().kind(), io::ErrorKind::InvalidData);
+        let unframed = read("HTTP/1.1 200 OK\r\n\r\n0123456789abcdef", 10);
+        assert_eq!(unframed.err().unwrap().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn close_or_missing_framing_is_not_reusable() {
+        let close = "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 2\r\n\r\nok";
+        let resp = read(close, 64).unwrap();
+        assert_eq!(resp.body, b"ok");
+        assert!(!resp.reusable);
+
+        let resp = read("HTTP/1.1 200 OK\r\n\r\nuntil eof", 64).unwrap();
+        assert_eq!(resp.body, b"until eof");
+        assert!(!resp.reusable);
+    }
+}
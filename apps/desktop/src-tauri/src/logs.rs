@@ -0,0 +1,296 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Instant;
+
+pub const LOG_LEVEL_ENV: &str = "PHOTOSENSE_LOG_LEVEL";
+const TAIL_CHUNK_SIZE: u64 = 8 * 1024;
+const ERROR_PATTERNS: [&str; 3] = ["error", "exception", "traceback"];
+const LISTENING_MARKER: &str = "Uvicorn running on ";
+const FORWARD_LINES_PER_SEC: f64 = 50.0;
+const FORWARD_BURST: f64 = 200.0;
+
+static FORWARD_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Error as u8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 4] = [
+        LogLevel::Debug,
+        LogLevel::Info,
+        LogLevel::Warn,
+        LogLevel::Error,
+    ];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_lowercase();
+        LogLevel::ALL.into_iter().find(|level| level.as_str() == value)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
+        }
+    }
+}
+
+/// Returns the last `count` lines of the file at `path`, oldest first. Reads
+/// backwards in fixed-size chunks so large logs are never loaded whole.
+pub fn read_last_lines(path: &Path, count: usize) -> io::Result<Vec<String>> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    let mut file = File::open(path)?;
+    let mut pos = file.metadata()?.len();
+    let mut buf: Vec<u8> = Vec::new();
+
+    while pos > 0 {
+        let read_size = TAIL_CHUNK_SIZE.min(pos);
+        pos -= read_size;
+        file.seek(SeekFrom::Start(pos))?;
+        let mut chunk = vec![0u8; read_size as usize];
+        file.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&buf);
+        buf = chunk;
+
+        // One extra newline is needed so the oldest returned line is complete.
+        if buf.iter().filter(|&&b| b == b'\n').count() > count {
+            break;
+        }
+    }
+
+    let text = String::from_utf8_lossy(&buf);
+    let lines: Vec<&str> = text.lines().collect();
+    let skip = lines.len().saturating_sub(count);
+    Ok(lines[skip..].iter().map(|l| l.to_string()).collect())
+}
+
+/// Reads whatever was appended to `path` since the last call. `offset` and
+/// `partial` carry state between calls: a trailing line without its newline
+/// is held back until it is complete, and a file that shrank (truncated or
+/// recreated) is read again from the start.
+pub fn read_new_lines(
+    path: &Path,
+    offset: &mut u64,
+    partial: &mut Vec<u8>,
+) -> io::Result<Vec<String>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    if len < *offset {
+        *offset = 0;
+        partial.clear();
+    }
+    if len == *offset {
+        return Ok(Vec::new());
+    }
+    file.seek(SeekFrom::Start(*offset))?;
+    let mut buf = Vec::new();
+    file.take(len - *offset).read_to_end(&mut buf)?;
+    *offset += buf.len() as u64;
+
+    partial.extend_from_slice(&buf);
+    let complete = match partial.iter().rposition(|&b| b == b'\n') {
+        Some(pos) => pos + 1,
+        None => return Ok(Vec::new()),
+    };
+    let rest = partial.split_off(complete);
+    let lines = String::from_utf8_lossy(partial).lines().map(str::to_string).collect();
+    *partial = rest;
+    Ok(lines)
+}
+
+/// Port from the most recent uvicorn "running on http://host:port" line.
+pub fn listening_port(lines: &[String]) -> Option<u16> {
+    lines.iter().rev().find_map(|line| {
+        let (_, rest) = line.split_once(LISTENING_MARKER)?;
+        let url = rest.split_whitespace().next()?;
+        url.trim_end_matches('/').rsplit(':').next()?.parse().ok()
+    })
+}
+
+pub fn is_error_line(line: &str) -> bool {
+    let lower = line.to_lowercase();
+    ERROR_PATTERNS.iter().any(|p| lower.contains(p))
+}
+
+/// Best-effort severity of a backend output line, based on the level names
+/// Python's logging prints and the shape of tracebacks.
+pub fn line_level(line: &str) -> LogLevel {
+    if is_error_line(line) {
+        return LogLevel::Error;
+    }
+    let lower = line.to_lowercase();
+    if lower.contains("warn") {
+        LogLevel::Warn
+    } else if lower.contains("debug") {
+        LogLevel::Debug
+    } else {
+        LogLevel::Info
+    }
+}
+
+/// Lowest severity of backend output that is forwarded to the frontend.
+pub fn forward_level() -> LogLevel {
+    LogLevel::ALL[FORWARD_LEVEL.load(Ordering::Relaxed) as usize]
+}
+
+pub fn set_forward_level(level: LogLevel) {
+    FORWARD_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Level requested through `PHOTOSENSE_LOG_LEVEL`, if set and valid.
+pub fn env_level() -> Option<LogLevel> {
+    let value = std::env::var(LOG_LEVEL_ENV).ok()?;
+    let level = LogLevel::parse(&value);
+    if level.is_none() {
+        eprintln!("[Error] Invalid {} value '{}'", LOG_LEVEL_ENV, value);
+    }
+    level
+}
+
+/// Throttles backend output on its way to the console and frontend: runs of
+/// identical lines collapse into one "<line> (repeated ×k)", and a token bucket
+/// caps the rest at `FORWARD_LINES_PER_SEC` with bursts of `FORWARD_BURST`.
+/// The log file is written before this and stays complete.
+pub struct OutputLimiter {
+    tokens: f64,
+    refilled: Instant,
+    last_line: Option<String>,
+    repeats: u32,
+    dropped: u32,
+}
+
+impl OutputLimiter {
+    pub fn new() -> Self {
+        OutputLimiter {
+            tokens: FORWARD_BURST,
+            refilled: Instant::now(),
+            last_line: None,
+            repeats: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Lines to forward now in place of `line`; often none.
+    pub fn push(&mut self, line: String) -> Vec<String> {
+        if self.last_line.as_ref() == Some(&line) {
+            self.repeats += 1;
+            return Vec::new();
+        }
+        let mut out = self.flush_repeats();
+        self.last_line = Some(line.clone());
+        out.push(line);
+        self.admit(out)
+    }
+
+    /// Anything still held back, for when the output stream ends.
+    pub fn finish(&mut self) -> Vec<String> {
+        let mut out = self.flush_repeats();
+        if self.dropped > 0 {
+            out.push(format!("({} lines suppressed)", self.dropped));
+            self.dropped = 0;
+        }
+        out
+    }
+
+    fn flush_repeats(&mut self) -> Vec<String> {
+        match (std::mem::take(&mut self.repeats), &self.last_line) {
+            (0, _) | (_, None) => Vec::new(),
+            (repeats, Some(line)) => vec![format!("{} (repeated ×{})", line, repeats)],
+        }
+    }
+
+    fn admit(&mut self, lines: Vec<String>) -> Vec<String> {
+        let now = Instant::now();
+        let refill = now.duration_since(self.refilled).as_secs_f64() * FORWARD_LINES_PER_SEC;
+        self.tokens = (self.tokens + refill).min(FORWARD_BURST);
+        self.refilled = now;
+
+        let mut out = Vec::new();
+        for line in lines {
+            if self.tokens < 1.0 {
+                self.dropped += 1;
+                continue;
+            }
+            if self.dropped > 0 {
+                out.push(format!("({} lines suppressed)", self.dropped));
+                self.dropped = 0;
+            }
+            self.tokens -= 1.0;
+            out.push(line);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn scratch_file(name: &str, contents: &str) -> PathBuf {
+        let name = format!("photosense-logs-{}-{name}", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn identical_lines_collapse_into_one() {
+        let mut limiter = OutputLimiter::new();
+        assert_eq!(limiter.push("a".into()), ["a"]);
+        for _ in 0..3 {
+            assert!(limiter.push("a".into()).is_empty());
+        }
+        assert_eq!(limiter.push("b".into()), ["a (repeated ×3)", "b"]);
+        assert!(limiter.push("b".into()).is_empty());
+        assert_eq!(limiter.finish(), ["b (repeated ×1)"]);
+    }
+
+    #[test]
+    fn lines_past_the_burst_are_dropped_then_counted() {
+        let mut limiter = OutputLimiter::new();
+        let admitted: usize = (0..FORWARD_BURST as usize)
+            .map(|i| limiter.push(format!("line {i}")).len())
+            .sum();
+        assert_eq!(admitted, FORWARD_BURST as usize);
+        assert!(limiter.push("dropped 1".into()).is_empty());
+        assert!(limiter.push("dropped 2".into()).is_empty());
+
+        // A second's worth of refill lets output resume, led by the count.
+        limiter.refilled -= Duration::from_secs(1);
+        assert_eq!(limiter.push("resumed".into()), ["(2 lines suppressed)", "resumed"]);
+        assert!(limiter.finish().is_empty());
+    }
+
+    #[test]
+    fn last_lines_span_chunk_boundaries() {
+        let lines: Vec<String> = (0..3000).map(|i| format!("line {i:05}")).collect();
+        let path = scratch_file("tail", &(lines.join("\n") + "\n"));
+        // 1500 lines of 11 bytes need two full chunks and part of a third.
+        let tail = read_last_lines(&path, 1500).unwrap();
+        assert_eq!(tail, lines[1500..]);
+        assert_eq!(read_last_lines(&path, 5000).unwrap(), lines);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn last_line_without_a_newline_is_included() {
+        let path = scratch_file("no-newline", "first\nsecond\nthird");
+        assert_eq!(read_last_lines(&path, 2).unwrap(), ["second", "third"]);
+        assert!(read_last_lines(&path, 0).unwrap().is_empty());
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -1,80 +1,489 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod arch;
+mod backend;
+mod config;
+mod crash;
+mod data_dir;
+mod diagnostics;
+mod http_pool;
+mod logs;
+mod metrics;
+mod reset;
+mod single_instance;
+mod transport;
+mod tray;
+mod update;
+mod usage;
+mod watchdog;
+
+use backend::{
+    auth_token, backend_port, cached_health, exposed_lan_address, external_backend, find_free_port,
+    graceful_shutdown_timeout, health_timeout, is_indexing, is_our_backend, is_port_open,
+    last_exit_code, max_restart_attempts, record_exit_code, restart_backoff, startup_timeout,
+    stop_child, BackendProcess, BackendProgress, BackendWaiter, Cleanup, Device, Priority,
+    ReadyInfo, SelfTestResult, WaitError, AUTH_TOKEN_ENV, BACKEND_PORT_ENV, BIND_HOST,
+    BIND_HOST_ENV, DEVICE_ENV, SHUTTING_DOWN,
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
 use std::fs::{self, OpenOptions};
-use std::io::Write;
+use std::io::{BufRead, BufReader, Write};
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard, OnceLock, PoisonError};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tauri::Manager;
 
+const MAX_PORT_ATTEMPTS: u32 = 3;
+const SPAWN_ATTEMPTS: u32 = 3;
+const SPAWN_RETRY_BASE_MS: u64 = 250;
+const HEALTHY_RESET_SECS: u64 = 60;
+const MAX_TAIL_LINES: usize = 1000;
+const ERROR_EVENT_MIN_INTERVAL_MS: u64 = 2000;
+const MIN_FREE_DISK_BYTES: u64 = 500 * 1024 * 1024;
+const REBUILD_INDEX_TIMEOUT_SECS: u64 = 600;
+const SELF_TEST_TIMEOUT_SECS: u64 = 300;
+const SELF_TEST_PROGRESS_SECS: u64 = 2;
+/// How long the main window's frontend has to call `frontend_ready`.
+const FRONTEND_LOAD_TIMEOUT_SECS: u64 = 30;
+/// Longest the window is held back for the initial port probe.
+const PRE_SHOW_PROBE_MS: u64 = 400;
+/// A job stops after the photo it is on, which can take a while on CPU.
+const CANCEL_WAIT_SECS: u64 = 60;
+const CANCEL_POLL_MS: u64 = 500;
+const TERMINAL_JOB_STATES: [&str; 3] = ["completed", "error", "cancelled"];
+
+const CRASH_LOOP_MAX_RESTARTS: usize = 3;
+const CRASH_LOOP_WINDOW_SECS: u64 = 60;
+const PING_TIMEOUT_RANGE: (u64, u64) = (1, 30);
+const PROXY_TIMEOUT_SECS: u64 = 30;
+const PROXY_METHODS: [&str; 5] = ["GET", "POST", "PUT", "PATCH", "DELETE"];
+/// Methods safe to send again after a transport error.
+const PROXY_RETRY_METHODS: [&str; 3] = ["GET", "PUT", "DELETE"];
+const PROXY_ATTEMPTS: u32 = 3;
+const PROXY_RETRY_DELAY_MS: u64 = 500;
+const PROXY_MAX_BODY_BYTES: usize = 8 * 1024 * 1024;
+/// How long a proxied request waits for a free slot before giving up.
+const PROXY_QUEUE_TIMEOUT_SECS: u64 = 10;
+const SPLASH_WINDOW: &str = "splashscreen";
+const APP_TITLE: &str = "PhotoSense-AI";
+const LOG_STREAM_INTERVAL_MS: u64 = 250;
+const LOG_STREAM_MAX_BATCH: usize = 200;
+const PRIORITY_CHECK_INTERVAL_SECS: u64 = 10;
+const FIRST_RUN_MARKER: &str = ".first-run-complete";
+const LAST_PORT_FILE: &str = "last-port";
+const RESOURCE_DIR_ENV: &str = "PHOTOSENSE_RESOURCE_DIR";
+const DEV_ENTRY_POINT: &str = "run_api.py";
+const BACKEND_LOG: &str = "backend.log";
+const LOG_FILE_ENV: &str = "PHOTOSENSE_LOG_FILE";
+/// First launches may download and initialise models before /health answers.
+const FIRST_RUN_TIMEOUT_SECS: u64 = 900;
+
+static SUPERVISOR_ACTIVE: AtomicBool = AtomicBool::new(false);
+static REBUILD_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+static SELF_TEST_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+static CLEANUP: Cleanup = Cleanup::new();
+static STARTUP_REPORTED: AtomicBool = AtomicBool::new(false);
+static CLOSE_CONFIRMED: AtomicBool = AtomicBool::new(false);
+static CLOSE_CHECK_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+static LOG_STREAM_GENERATION: AtomicU64 = AtomicU64::new(0);
+static LOG_FILE_FAILED: AtomicBool = AtomicBool::new(false);
+static UPDATE_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+static CLEAR_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+static MODELS_READY: AtomicBool = AtomicBool::new(false);
+static LOG_FILE: OnceLock<PathBuf> = OnceLock::new();
+static FRONTEND_READY: AtomicBool = AtomicBool::new(false);
+static PROXY_IN_FLIGHT: Mutex<usize> = Mutex::new(0);
+static PROXY_SLOT_FREED: Condvar = Condvar::new();
+
 struct BackendState {
     child: Option<std::process::Child>,
+    port: u16,
+    restart_count: u32,
+    total_restarts: u32,
+    recent_restarts: VecDeque<Instant>,
+    backend_path: Option<PathBuf>,
+    spawn_attempts: u32,
+    started_at: Option<Instant>,
+    watchdog: Option<watchdog::Watchdog>,
+    last_failure: Option<BackendFailure>,
+    warnings: Vec<String>,
+    launch_info: Option<LaunchInfo>,
+    /// Recent watchdog health-check round trips, oldest first.
+    health_latencies: VecDeque<Duration>,
+    /// User-chosen priority; `None` follows indexing activity.
+    priority_override: Option<Priority>,
+    device: Device,
+    /// Set while attached to a PhotoSense backend of another version.
+    foreign_backend: Option<ForeignBackend>,
+    /// Last priority set, keyed by the PID it was applied to.
+    applied_priority: Option<(u32, Priority)>,
+    last_self_test: Option<SelfTestResult>,
+}
+
+impl BackendState {
+    fn new(port: u16) -> Self {
+        BackendState {
+            child: None,
+            port,
+            restart_count: 0,
+            total_restarts: 0,
+            recent_restarts: VecDeque::with_capacity(CRASH_LOOP_MAX_RESTARTS + 1),
+            backend_path: None,
+            spawn_attempts: 0,
+            started_at: None,
+            watchdog: None,
+            last_failure: None,
+            warnings: Vec::new(),
+            launch_info: None,
+            health_latencies: VecDeque::with_capacity(watchdog::LATENCY_SAMPLES),
+            priority_override: None,
+            device: backend::device_from_env(),
+            foreign_backend: None,
+            applied_priority: None,
+            last_self_test: None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct SelfTestProgress {
+    stage: &'static str,
+    elapsed_secs: u64,
+}
+
+#[derive(Clone, Serialize)]
+struct BackendFailure {
+    message: String,
+    exit_code: Option<i32>,
+    /// Which startup check failed, when the backend never became ready.
+    reason: Option<&'static str>,
+}
+
+#[derive(Clone, Serialize)]
+struct VersionInfo {
+    app_version: String,
+    backend_version: String,
+    matches: bool,
+}
+
+#[derive(Serialize)]
+struct StartupReport {
+    status: &'static str,
+    message: Option<String>,
+    port: u16,
+    backend_path: Option<String>,
+    spawn_attempts: u32,
+    elapsed_ms: Option<u64>,
+    os: &'static str,
+    arch: &'static str,
+    app_version: String,
+}
+
+/// How the backend was last launched. `exists` and `is_executable` describe
+/// the executable at the time of the query.
+#[derive(Clone, Serialize)]
+struct LaunchInfo {
+    executable: String,
+    working_dir: String,
+    args: Vec<String>,
+    env: BTreeMap<String, String>,
+    sanitized_env: bool,
+    exists: bool,
+    is_executable: bool,
+}
+
+#[derive(Clone, Serialize)]
+struct BackendDegraded {
+    port: u16,
+    warnings: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct BackendStatus {
+    running: bool,
+    port: u16,
+    pid: Option<u32>,
+    uptime_secs: Option<u64>,
+    restart_count: u32,
+    degraded: bool,
+    warnings: Vec<String>,
+    models_ready: bool,
+    transport: &'static str,
+    foreign_backend: Option<ForeignBackend>,
+}
+
+#[derive(Clone, Serialize)]
+struct ForeignBackend {
+    port: u16,
+    version: Option<String>,
+    pid: Option<u32>,
+    app_version: String,
+}
+
+#[derive(Serialize)]
+struct ProxyResponse {
+    status: u16,
+    body: String,
+}
+
+#[derive(Serialize)]
+struct CancelResult {
+    job_id: String,
+    /// False when the job had already finished, or is unknown to the backend.
+    cancelled: bool,
+    /// The job's final status, or "unknown".
+    status: String,
+}
+
+#[derive(Serialize)]
+struct EffectivePort {
+    requested: u16,
+    actual: u16,
+    corrected: bool,
+}
+
+#[derive(Serialize)]
+struct PingResult {
+    ok: bool,
+    latency_ms: u64,
+    status_code: Option<u16>,
 }
 
+#[derive(Serialize)]
+struct BackendHealth {
+    port: u16,
+    healthy: bool,
+    /// True when the result came from the short-lived health cache, in which
+    /// case `response_time_ms` is that of the probe that filled it.
+    cached: bool,
+    response_time_ms: u64,
+    pid: Option<u32>,
+    uptime_secs: Option<u64>,
+}
+
+fn data_dir(app: &tauri::AppHandle) -> PathBuf {
+    data_dir::resolve(&app.config()).unwrap_or_else(std::env::temp_dir)
+}
+
+/// Directory holding the backend log.
 fn log_path(app: &tauri::AppHandle) -> PathBuf {
-    let base = app
-        .path_resolver()
-        .app_data_dir()
-        .unwrap_or_else(std::env::temp_dir);
-    base.join("logs")
+    let file = log_file(app);
+    file.parent().map(PathBuf::from).unwrap_or_else(|| data_dir(app).join("logs"))
+}
+
+/// `backend.log` in the data dir, or the path in `PHOTOSENSE_LOG_FILE` if
+/// its directory can be created and the file opened for appending. Decided
+/// once, on first use.
+fn log_file(app: &tauri::AppHandle) -> PathBuf {
+    LOG_FILE
+        .get_or_init(|| {
+            let default = data_dir(app).join("logs").join(BACKEND_LOG);
+            let custom = match std::env::var_os(LOG_FILE_ENV).filter(|path| !path.is_empty()) {
+                Some(custom) => PathBuf::from(custom),
+                None => return default,
+            };
+            let writable = custom
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| OpenOptions::new().create(true).append(true).open(&custom));
+            match writable {
+                Ok(_) => custom,
+                Err(e) => {
+                    eprintln!(
+                        "[Warning] Cannot write log file {}: {e}; using {} instead",
+                        custom.display(),
+                        default.display()
+                    );
+                    default
+                }
+            }
+        })
+        .clone()
+}
+
+/// True until the backend has come up healthy once with this data dir.
+fn is_first_run(app: &tauri::AppHandle) -> bool {
+    !data_dir(app).join(FIRST_RUN_MARKER).exists()
+}
+
+/// Port the backend last came up healthy on, if one was recorded.
+fn read_last_port(data_dir: &std::path::Path) -> Option<u16> {
+    fs::read_to_string(data_dir.join(LAST_PORT_FILE))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .filter(|port| *port >= 1024)
+}
+
+fn save_last_port(app: &tauri::AppHandle, port: u16) {
+    let dir = data_dir(app);
+    let result = fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(dir.join(LAST_PORT_FILE), port.to_string()));
+    if let Err(e) = result {
+        log_line(app, &format!("[Warning] Failed to save backend port: {e}"));
+    }
+}
+
+fn mark_first_run_complete(app: &tauri::AppHandle) {
+    let dir = data_dir(app);
+    let result = fs::create_dir_all(&dir).and_then(|_| fs::write(dir.join(FIRST_RUN_MARKER), ""));
+    if let Err(e) = result {
+        log_line(app, &format!("[Warning] Failed to write first-run marker: {e}"));
+    }
 }
 
+/// Appends `message` to backend.log. If the log can't be written (usually a
+/// permissions problem with the data dir) the message goes to stderr instead,
+/// with a one-time warning explaining why.
 fn log_line(app: &tauri::AppHandle, message: &str) {
-    let log_dir = log_path(app);
-    if fs::create_dir_all(&log_dir).is_ok() {
-        let log_file = log_dir.join("backend.log");
-        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(log_file) {
-            let _ = writeln!(file, "{}", message);
+    let log_file = log_file(app);
+    let result = fs::create_dir_all(log_path(app)).and_then(|_| {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_file)?;
+        writeln!(file, "{}", message)
+    });
+    if let Err(e) = result {
+        if !LOG_FILE_FAILED.swap(true, Ordering::SeqCst) {
+            eprintln!(
+                "[Warning] Cannot write to {}: {e}; logging to console instead",
+                log_file.display()
+            );
         }
+        eprintln!("{}", message);
     }
 }
 
-fn is_backend_ready() -> bool {
-    let addr: std::net::SocketAddr = match "127.0.0.1:8000".parse() {
-        Ok(a) => a,
-        Err(_) => return false,
-    };
-    std::net::TcpStream::connect_timeout(&addr, Duration::from_secs(1)).is_ok()
+#[cfg(target_os = "windows")]
+const BACKEND_BINARY: &str = "photosense-backend.exe";
+#[cfg(target_os = "windows")]
+const BACKEND_BINARY_ALT: &str = "photosense-backend";
+#[cfg(not(target_os = "windows"))]
+const BACKEND_BINARY: &str = "photosense-backend";
+#[cfg(not(target_os = "windows"))]
+const BACKEND_BINARY_ALT: &str = "photosense-backend.exe";
+const BACKEND_RESOURCE_DIR: &str = "resources/backend";
+const RESOLVE_RETRY_DELAY_MS: u64 = 500;
+
+#[derive(Clone, Serialize)]
+struct ArchMismatch {
+    app_arch: &'static str,
+    backend_arch: &'static str,
+    message: String,
 }
 
-fn wait_for_backend(max_seconds: u32) -> bool {
-    for i in 1..=max_seconds * 2 {
-        if is_backend_ready() {
-            println!("[PhotoSense] Backend ready after ~{}ms", i * 500);
-            return true;
+#[derive(Clone, Serialize)]
+struct SidecarMissing {
+    expected: String,
+    found: Vec<String>,
+}
+
+fn resolve_backend_binary(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let updated = update::active_binary(&data_dir(app), &[BACKEND_BINARY, BACKEND_BINARY_ALT]);
+    if let Some(path) = updated {
+        log_line(app, &format!("[PhotoSense] Using updated backend at {}", path.display()));
+        return Ok(path);
+    }
+    let backend_name = format!("{}/{}", BACKEND_RESOURCE_DIR, BACKEND_BINARY);
+    let mut resolved = None;
+    // After an in-place update the first resolution can point at a file the
+    // updater just removed, so resolve again after a short pause.
+    for attempt in 1..=2 {
+        if attempt > 1 {
+            thread::sleep(Duration::from_millis(RESOLVE_RETRY_DELAY_MS));
         }
-        if i % 4 == 0 {
-            println!("[PhotoSense] Waiting for backend... ({}s)", i / 2);
+        for name in [BACKEND_BINARY, BACKEND_BINARY_ALT] {
+            let candidate = app
+                .path_resolver()
+                .resolve_resource(format!("{}/{}", BACKEND_RESOURCE_DIR, name));
+            if let Some(path) = candidate.as_ref() {
+                if path.exists() {
+                    if attempt > 1 || name != BACKEND_BINARY {
+                        let msg = format!("[PhotoSense] Resolved backend at {}", path.display());
+                        log_line(app, &msg);
+                    }
+                    return Ok(path.clone());
+                }
+                let msg = format!(
+                    "[Warning] Backend not found at {} (attempt {})",
+                    path.display(),
+                    attempt
+                );
+                log_line(app, &msg);
+            }
+            if name == BACKEND_BINARY {
+                resolved = candidate;
+            }
         }
-        thread::sleep(Duration::from_millis(500));
     }
-    false
-}
 
-fn start_backend(app: &tauri::AppHandle) -> Result<std::process::Child, String> {
-    println!("[PhotoSense] Starting backend...");
-    log_line(app, "[PhotoSense] Starting backend...");
-
-    #[cfg(target_os = "windows")]
-    let backend_name = "resources/backend/photosense-backend.exe";
-    #[cfg(not(target_os = "windows"))]
-    let backend_name = "resources/backend/photosense-backend";
+    // List what actually shipped so packaging mismatches show up in the log.
+    let resource_dir = app.path_resolver().resolve_resource(BACKEND_RESOURCE_DIR);
+    let found: Vec<String> = resource_dir
+        .as_ref()
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .map(|entries| {
+            let mut names: Vec<String> = entries
+                .filter_map(Result::ok)
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .collect();
+            names.sort();
+            names
+        })
+        .unwrap_or_default();
+    let msg = format!(
+        "[Error] Backend binary {} not found (looked in {}); directory contains: [{}]",
+        BACKEND_BINARY,
+        resource_dir
+            .as_ref()
+            .map(|d| d.display().to_string())
+            .unwrap_or_else(|| "<unresolved>".to_string()),
+        found.join(", ")
+    );
+    eprintln!("{}", msg);
+    log_line(app, &msg);
+    let _ = app.emit_all(
+        "sidecar-missing",
+        SidecarMissing {
+            expected: BACKEND_BINARY.to_string(),
+            found,
+        },
+    );
 
-    let backend_path = app
-        .path_resolver()
-        .resolve_resource(backend_name)
-        .ok_or_else(|| format!("Backend resource not found: {}", backend_name))?;
+    Err(match resolved {
+        Some(path) => format!("Backend executable does not exist at: {}", path.display()),
+        None => format!("Backend resource not found: {}", backend_name),
+    })
+}
 
-    if !backend_path.exists() {
-        return Err(format!(
-            "Backend executable does not exist at: {}",
-            backend_path.display()
-        ));
+/// Installs any staged update, then resolves and prepares the bundled
+/// backend executable. Returns its path, the directory holding it and any
+/// architecture mismatch warning.
+fn prepare_bundled_backend(
+    app: &tauri::AppHandle,
+) -> Result<(PathBuf, PathBuf, Option<String>), String> {
+    match update::promote_staged(&data_dir(app)) {
+        Ok(Some(version)) => {
+            let msg = format!("[PhotoSense] Installed backend update {}", version);
+            println!("{}", msg);
+            log_line(app, &msg);
+        }
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("[Warning] {}", e);
+            log_line(app, &format!("[Warning] {}", e));
+        }
     }
 
+    let backend_path = resolve_backend_binary(app)?;
+
     let backend_dir = match backend_path.parent() {
         Some(d) => d.to_path_buf(),
         None => return Err(format!("Backend directory not found for: {}", backend_path.display())),
@@ -90,92 +499,2608 @@ fn start_backend(app: &tauri::AppHandle) -> Result<std::process::Child, String>
         }
     }
 
+    #[cfg(target_os = "macos")]
+    remove_quarantine(app, &backend_path);
+
     log_line(app, &format!("[PhotoSense] Backend path: {}", backend_path.display()));
-    log_line(app, &format!("[PhotoSense] Backend cwd: {}", backend_dir.display()));
+    let arch_mismatch = check_backend_arch(app, &backend_path);
+    Ok((backend_path, backend_dir, arch_mismatch))
+}
 
-    let log_dir = log_path(app);
-    let _ = fs::create_dir_all(&log_dir);
-    let log_file = log_dir.join("backend.log");
-    
-    let data_dir_str = log_dir
-        .parent()
-        .map(|p| p.to_string_lossy().to_string())
-        .unwrap_or_else(|| backend_dir.to_string_lossy().to_string());
+fn launch_backend(app: &tauri::AppHandle, port: u16) -> Result<std::process::Child, String> {
+    println!("[PhotoSense] Starting backend on port {}...", port);
+    log_line(app, &format!("[PhotoSense] Starting backend on port {}...", port));
+
+    let dev_source = backend::dev_python_source();
+    let dev_mode = dev_source.is_some();
+    let (backend_path, resource_dir, mut backend_argv, arch_mismatch) = match dev_source {
+        Some((python, source_dir)) => {
+            if !source_dir.join(DEV_ENTRY_POINT).is_file() {
+                return Err(format!(
+                    "{} not found in {}",
+                    DEV_ENTRY_POINT,
+                    source_dir.display()
+                ));
+            }
+            let msg = format!(
+                "[PhotoSense] Dev mode: running {} {} in {}",
+                python.display(),
+                DEV_ENTRY_POINT,
+                source_dir.display()
+            );
+            println!("{}", msg);
+            log_line(app, &msg);
+            (python, source_dir, vec![DEV_ENTRY_POINT.to_string()], None)
+        }
+        None => {
+            let (backend_path, backend_dir, arch_mismatch) = prepare_bundled_backend(app)?;
+            (backend_path, backend_dir, Vec::new(), arch_mismatch)
+        }
+    };
+    lock_state(&app.state::<Mutex<BackendState>>()).backend_path = Some(backend_path.clone());
+
+    let _ = fs::create_dir_all(log_path(app));
+    let log_file = log_file(app);
     
+    let data_dir_str = data_dir(app).to_string_lossy().to_string();
+
+    check_disk_space(app, std::path::Path::new(&data_dir_str));
+
+    // Release bundles are read-only once signed, so the bundled backend runs
+    // from the data dir and finds its resources through RESOURCE_DIR_ENV. A
+    // source checkout is writable and run_api.py expects to start there.
+    let working_dir = if dev_mode {
+        resource_dir.clone()
+    } else {
+        PathBuf::from(&data_dir_str)
+    };
+    log_line(app, &format!("[PhotoSense] Backend cwd: {}", working_dir.display()));
+
     let log_handle = OpenOptions::new()
         .create(true)
         .append(true)
         .open(&log_file)
         .map_err(|e| format!("Failed to open backend log file: {e}"))?;
 
-    let log_handle_clone = log_handle.try_clone()
+    let stdout_log = log_handle.try_clone()
         .map_err(|e| format!("Failed to clone log handle: {e}"))?;
 
-    let child = Command::new(&backend_path)
-        .current_dir(&backend_dir)
-        .env("PHOTOSENSE_DATA_DIR", data_dir_str)
-        .stdout(Stdio::from(log_handle_clone))
-        .stderr(Stdio::from(log_handle))
-        .spawn()
-        .map_err(|e| format!("Failed to spawn backend: {e}"))?;
+    let extra_args = backend::backend_args();
+    if !extra_args.is_empty() {
+        log_line(app, &format!("[PhotoSense] Backend args: {:?}", extra_args));
+    }
+    backend_argv.extend(extra_args);
+
+    let mut env = vec![
+        (data_dir::DATA_DIR_ENV, data_dir_str),
+        (BACKEND_PORT_ENV, port.to_string()),
+        (BIND_HOST_ENV, BIND_HOST.to_string()),
+        (AUTH_TOKEN_ENV, auth_token().to_string()),
+        (RESOURCE_DIR_ENV, resource_dir.to_string_lossy().to_string()),
+        (
+            DEVICE_ENV,
+            lock_state(&app.state::<Mutex<BackendState>>()).device.as_str().to_string(),
+        ),
+    ];
+    if let Some(socket) = transport::socket_path() {
+        transport::prepare_socket()?;
+        log_line(app, &format!("[PhotoSense] Backend socket: {}", socket.display()));
+        env.push((transport::UDS_PATH_ENV, socket.to_string_lossy().to_string()));
+    }
+    if dev_mode {
+        // Otherwise Python block-buffers output to a pipe and logs lag behind.
+        env.push(("PYTHONUNBUFFERED", "1".to_string()));
+    }
+    let sanitized_env = !dev_mode && backend::sanitize_env_enabled();
+    // Recorded before spawning so failed launches can be inspected too.
+    lock_state(&app.state::<Mutex<BackendState>>()).launch_info = Some(LaunchInfo {
+        executable: backend_path.to_string_lossy().to_string(),
+        working_dir: working_dir.to_string_lossy().to_string(),
+        args: backend_argv.clone(),
+        env: env
+            .iter()
+            .map(|(name, value)| {
+                let value = if *name == AUTH_TOKEN_ENV { "<redacted>" } else { value };
+                (name.to_string(), value.to_string())
+            })
+            .collect(),
+        sanitized_env,
+        exists: false,
+        is_executable: false,
+    });
+
+    let mut command = Command::new(&backend_path);
+    if sanitized_env {
+        let (inherited, dropped) = backend::sanitized_env();
+        if !dropped.is_empty() {
+            let msg = format!("[PhotoSense] Not passing to backend: {}", dropped.join(", "));
+            log_line(app, &msg);
+        }
+        command.env_clear().envs(inherited);
+    }
+    command
+        .args(&backend_argv)
+        .current_dir(&working_dir)
+        .envs(env)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(backend::CREATE_NEW_PROCESS_GROUP);
+    }
+
+    let mut child = spawn_with_retry(app, &mut command).map_err(|e| {
+        #[cfg(target_os = "macos")]
+        if e.kind() == std::io::ErrorKind::PermissionDenied {
+            report_gatekeeper_block(app, &backend_path);
+        }
+        match &arch_mismatch {
+            Some(mismatch) => format!("Failed to spawn backend: {e}. {}", mismatch),
+            None => format!("Failed to spawn backend: {e}"),
+        }
+    })?;
+
+    MODELS_READY.store(false, Ordering::SeqCst);
+    if let Some(stdout) = child.stdout.take() {
+        forward_backend_output(app.clone(), stdout, stdout_log);
+    }
+    if let Some(stderr) = child.stderr.take() {
+        forward_backend_output(app.clone(), stderr, log_handle);
+    }
 
     Ok(child)
 }
 
-#[tauri::command]
-fn check_backend_status() -> bool {
-    is_backend_ready()
+/// Retries spawn errors that can be transient, such as the executable being
+/// briefly locked by antivirus right after extraction. A missing or
+/// unrunnable executable fails straight away.
+fn spawn_with_retry(
+    app: &tauri::AppHandle,
+    command: &mut Command,
+) -> std::io::Result<std::process::Child> {
+    let mut delay = Duration::from_millis(SPAWN_RETRY_BASE_MS);
+    let mut attempt = 1;
+    loop {
+        let e = match command.spawn() {
+            Ok(child) => return Ok(child),
+            Err(e) => e,
+        };
+        let permanent = matches!(
+            e.kind(),
+            std::io::ErrorKind::NotFound | std::io::ErrorKind::InvalidInput
+        );
+        if permanent || attempt == SPAWN_ATTEMPTS {
+            return Err(e);
+        }
+        let msg = format!(
+            "[Warning] Backend spawn failed (attempt {}/{}): {e}, retrying in {} ms",
+            attempt,
+            SPAWN_ATTEMPTS,
+            delay.as_millis()
+        );
+        eprintln!("{}", msg);
+        log_line(app, &msg);
+        thread::sleep(delay);
+        delay *= 2;
+        attempt += 1;
+    }
 }
 
-fn main() {
-    tauri::Builder::default()
-        .manage(Mutex::new(BackendState { child: None }))
-        .setup(|app| {
+/// Downloaded copies of the app carry `com.apple.quarantine`, which can stop
+/// the bundled backend from launching even when it is executable.
+#[cfg(target_os = "macos")]
+fn remove_quarantine(app: &tauri::AppHandle, backend_path: &std::path::Path) {
+    let output = Command::new("xattr")
+        .args(["-d", "com.apple.quarantine"])
+        .arg(backend_path)
+        .output();
+    match output {
+        Ok(out) if out.status.success() => {
+            log_line(app, "[PhotoSense] Removed quarantine attribute from backend");
+        }
+        Ok(out) => {
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            // Not quarantined in the first place.
+            if !stderr.contains("No such xattr") {
+                log_line(
+                    app,
+                    &format!("[Warning] Could not remove backend quarantine: {}", stderr.trim()),
+                );
+            }
+        }
+        Err(e) => log_line(app, &format!("[Warning] Could not run xattr: {e}")),
+    }
+}
+
+#[cfg(target_os = "macos")]
+#[derive(Clone, Serialize)]
+struct GatekeeperBlocked {
+    path: String,
+    message: String,
+}
+
+#[cfg(target_os = "macos")]
+fn report_gatekeeper_block(app: &tauri::AppHandle, backend_path: &std::path::Path) {
+    let message = format!(
+        "macOS blocked the PhotoSense backend from running. Open System Settings > Privacy & \
+         Security and choose \"Open Anyway\", or run: xattr -dr com.apple.quarantine \"{}\"",
+        backend_path.parent().unwrap_or(backend_path).display()
+    );
+    eprintln!("[Error] {}", message);
+    log_line(app, &format!("[Error] {}", message));
+    let _ = app.emit_all(
+        "gatekeeper-blocked",
+        GatekeeperBlocked {
+            path: backend_path.to_string_lossy().to_string(),
+            message,
+        },
+    );
+}
+
+/// Warns with an `arch-mismatch` event when the backend binary was built for
+/// a different CPU than this app, and returns the explanation. Spawning is
+/// still attempted since emulation (Rosetta, Windows on ARM) may cope.
+fn check_backend_arch(app: &tauri::AppHandle, backend_path: &std::path::Path) -> Option<String> {
+    let backend_arch = match arch::binary_arch(backend_path) {
+        Ok(Some(backend_arch)) if !arch::is_compatible(backend_arch) => backend_arch,
+        _ => return None,
+    };
+    let app_arch = std::env::consts::ARCH;
+    let message = format!(
+        "The backend is built for {} but this app is running as {}; reinstall the {} build of PhotoSense-AI",
+        backend_arch, app_arch, app_arch
+    );
+    eprintln!("[Warning] {}", message);
+    log_line(app, &format!("[Warning] {}", message));
+    let _ = app.emit_all(
+        "arch-mismatch",
+        ArchMismatch {
+            app_arch,
+            backend_arch,
+            message: message.clone(),
+        },
+    );
+    Some(message)
+}
+
+fn check_disk_space(app: &tauri::AppHandle, data_dir: &std::path::Path) {
+    match usage::free_disk_space(data_dir) {
+        Ok(free) if free < MIN_FREE_DISK_BYTES => {
+            let msg = format!(
+                "[Warning] Only {} MB free on the data volume ({})",
+                free / (1024 * 1024),
+                data_dir.display()
+            );
+            eprintln!("{}", msg);
+            log_line(app, &msg);
+            let _ = app.emit_all("low-disk-space", free);
+        }
+        Ok(_) => {}
+        Err(e) => log_line(app, &format!("[Warning] Could not check free disk space: {e}")),
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct DataDirCheck {
+    path: String,
+    writable: bool,
+    error: Option<String>,
+}
+
+/// Creates the data dir and its logs subdir and round-trips a probe file, so
+/// a permissions problem shows up before the backend trips over it.
+fn probe_data_dir(data_dir: &std::path::Path) -> std::io::Result<()> {
+    fs::create_dir_all(data_dir.join("logs"))?;
+    let probe = data_dir.join(format!(".write-test-{}", std::process::id()));
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&probe)?
+        .write_all(b"ok")?;
+    fs::remove_file(&probe)
+}
+
+fn check_data_dir_writable(app: &tauri::AppHandle) -> DataDirCheck {
+    let data_dir = data_dir(app);
+    let error = probe_data_dir(&data_dir).err().map(|e| e.to_string());
+    DataDirCheck {
+        path: data_dir.to_string_lossy().to_string(),
+        writable: error.is_none(),
+        error,
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct BackendLogLine {
+    level: &'static str,
+    line: String,
+}
+
+/// One `backend-log-line` event. Lines appended within one poll interval are
+/// sent together; beyond `LOG_STREAM_MAX_BATCH` only the newest are kept and
+/// `dropped` counts the rest.
+#[derive(Clone, Serialize)]
+struct BackendLogBatch {
+    lines: Vec<String>,
+    dropped: usize,
+}
+
+/// Copies backend output into the log file and forwards lines at or above
+/// the current forward level to the frontend, deduplicated and throttled by
+/// `OutputLimiter`. Error lines are further rate-limited. The first line
+/// containing the models-ready marker emits `models-ready`.
+fn forward_backend_output(
+    app: tauri::AppHandle,
+    output: impl std::io::Read + Send + 'static,
+    mut log: fs::File,
+) {
+    let tee = backend::debug_mode();
+    let marker = backend::models_ready_marker();
+    thread::spawn(move || {
+        let min_interval = Duration::from_millis(ERROR_EVENT_MIN_INTERVAL_MS);
+        let mut last_emit: Option<Instant> = None;
+        let mut forward = |line: String| {
+            if tee {
+                println!("[Backend] {}", line);
+            }
+            let level = logs::line_level(&line);
+            if level < logs::forward_level() {
+                return;
+            }
+            if level != logs::LogLevel::Error {
+                let _ = app.emit_all(
+                    "backend-log",
+                    BackendLogLine {
+                        level: level.as_str(),
+                        line,
+                    },
+                );
+            } else if last_emit.is_none_or(|t| t.elapsed() >= min_interval) {
+                last_emit = Some(Instant::now());
+                let _ = app.emit_all("backend-error-log", line);
+            }
+        };
+
+        let mut limiter = logs::OutputLimiter::new();
+        for line in BufReader::new(output).lines().map_while(Result::ok) {
+            let _ = log.write_all(format!("{}\n", line).as_bytes());
+            if line.contains(marker) && !MODELS_READY.swap(true, Ordering::SeqCst) {
+                log_line(&app, "[PhotoSense] Backend models loaded");
+                let _ = app.emit_all("models-ready", ());
+            }
+            limiter.push(line).into_iter().for_each(&mut forward);
+        }
+        limiter.finish().into_iter().for_each(forward);
+    });
+}
+
+/// A PhotoSense backend of another version, probably from a different
+/// install, is treated as foreign: it is used for now, and the UI is told
+/// so it can offer `resolve_foreign_backend`.
+fn report_if_foreign(app: &tauri::AppHandle, port: u16, identity: backend::BackendIdentity) {
+    let app_version = env!("CARGO_PKG_VERSION");
+    let foreign = match identity.version.as_deref() {
+        Some(version) => version != app_version,
+        None => true,
+    };
+    if !foreign {
+        return;
+    }
+    let msg = format!(
+        "[Warning] Backend on port {} is PhotoSense {} (pid {}), this app is {}",
+        port,
+        identity.version.as_deref().unwrap_or("unknown"),
+        identity.pid.map_or("unknown".to_string(), |pid| pid.to_string()),
+        app_version
+    );
+    eprintln!("{}", msg);
+    log_line(app, &msg);
+    let foreign = ForeignBackend {
+        port,
+        version: identity.version,
+        pid: identity.pid,
+        app_version: app_version.to_string(),
+    };
+    lock_state(&app.state::<Mutex<BackendState>>()).foreign_backend = Some(foreign.clone());
+    let _ = app.emit_all("foreign-backend-detected", foreign);
+}
+
+fn spawn_backend(
+    app: &tauri::AppHandle,
+    preferred_port: u16,
+) -> Result<(Option<std::process::Child>, u16), String> {
+    if is_port_open(preferred_port) {
+        if let Some(identity) = backend::identify_backend(preferred_port) {
+            let msg = format!(
+                "[PhotoSense] Attaching to running backend on port {}",
+                preferred_port
+            );
+            println!("{}", msg);
+            log_line(app, &msg);
+            report_if_foreign(app, preferred_port, identity);
+            return Ok((None, preferred_port));
+        }
+        let msg = format!(
+            "[Warning] Port {} is held by another service, not PhotoSense",
+            preferred_port
+        );
+        eprintln!("{}", msg);
+        log_line(app, &msg);
+        let _ = app.emit_all("port-conflict", preferred_port);
+    }
+
+    let check = check_data_dir_writable(app);
+    if let Some(error) = &check.error {
+        let msg = format!("Data directory {} is not writable: {}", check.path, error);
+        eprintln!("[Error] {}", msg);
+        log_line(app, &format!("[Error] {msg}"));
+        let _ = app.emit_all("data-dir-unwritable", &check);
+        return Err(msg);
+    }
+
+    let mut port = preferred_port;
+    for attempt in 1..=MAX_PORT_ATTEMPTS {
+        lock_state(&app.state::<Mutex<BackendState>>()).spawn_attempts = attempt;
+        if is_port_open(port) {
+            let msg = format!("[PhotoSense] Port {} is already in use (attempt {})", port, attempt);
+            println!("{}", msg);
+            log_line(app, &msg);
+            port = find_free_port().ok_or("Failed to find a free port for the backend")?;
+            // The port was released by find_free_port; re-check before spawning
+            // in case another process grabbed it in the meantime.
+            if is_port_open(port) {
+                continue;
+            }
+        }
+        return launch_backend(app, port).map(|child| (Some(child), port));
+    }
+    Err(format!(
+        "No free port available for the backend after {} attempts",
+        MAX_PORT_ATTEMPTS
+    ))
+}
+
+fn announce_when_ready(app: tauri::AppHandle, port: u16) {
+    set_status_title(&app, Some("Backend Starting…"));
+    thread::spawn(move || {
+        let first_run = is_first_run(&app);
+        let timeout = if first_run {
+            let msg = "[PhotoSense] First run: allowing extra time for model setup";
+            println!("{}", msg);
+            log_line(&app, msg);
+            startup_timeout().max(Duration::from_secs(FIRST_RUN_TIMEOUT_SECS))
+        } else {
+            startup_timeout()
+        };
+        let on_progress = |progress: BackendProgress| {
+            if first_run {
+                let _ = app.emit_all("first-run-setup", progress.clone());
+            }
+            let _ = app.emit_all("backend-progress", progress);
+        };
+        let mut outcome = BackendWaiter::new(timeout).wait(port, &on_progress);
+        let grace = backend::startup_grace();
+        let timed_out = match outcome {
+            Err(e) if e.kind() != "wrong_service" && !grace.is_zero() => Some(e.elapsed()),
+            _ => None,
+        };
+        if let Some(waited) = timed_out {
+            let msg = format!(
+                "[Warning] Backend not ready after {} s, waiting up to {} s more",
+                waited.as_secs(),
+                grace.as_secs()
+            );
+            eprintln!("{}", msg);
+            log_line(&app, &msg);
+            let _ = app.emit_all("backend-slow-start", waited.as_millis() as u64);
+            let slow = Duration::from_millis(backend::GRACE_POLL_INTERVAL_MS);
+            let waiter = BackendWaiter {
+                min_interval: slow,
+                max_interval: slow,
+                ..BackendWaiter::new(grace)
+            };
+            outcome = waiter
+                .wait(port, &on_progress)
+                .map(|ready| ReadyInfo {
+                    elapsed: waited + ready.elapsed,
+                })
+                .map_err(|e| e.after(waited));
+        }
+        let (result, elapsed) = match outcome {
+            Ok(ready) => ("ready", ready.elapsed),
+            Err(e) => (e.kind(), e.elapsed()),
+        };
+        record_metric(
+            &app,
+            metrics::MetricEvent::Startup {
+                outcome: result.to_string(),
+                elapsed_ms: elapsed.as_millis() as u64,
+            },
+        );
+        if let Err(WaitError::WrongService(elapsed)) = outcome {
+            let msg = format!("Port {} is in use by another application", port);
+            eprintln!("[Error] {}", msg);
+            log_line(&app, &format!("[Error] {}", msg));
+            write_startup_report(&app, "wrong_service", Some(msg.clone()), Some(elapsed));
+            report_failure(
+                &app,
+                "backend-failed",
+                BackendFailure {
+                    message: msg,
+                    exit_code: last_exit_code(),
+                    reason: Some("wrong_service"),
+                },
+            );
+        } else if let Ok(ReadyInfo { elapsed }) = outcome {
+            println!("[PhotoSense] Backend ready in {} ms", elapsed.as_millis());
+            log_line(&app, &format!("[PhotoSense] Backend ready in {} ms", elapsed.as_millis()));
+            write_startup_report(&app, "ready", None, Some(elapsed));
+            if first_run {
+                mark_first_run_complete(&app);
+            }
+            if !external_backend() {
+                save_last_port(&app, port);
+            }
             println!("================================================");
-            println!("  PhotoSense-AI Starting");
+            println!("  PhotoSense-AI Ready!");
+            println!("  Backend: {}", backend::backend_url(port, ""));
             println!("================================================");
+            let state = app.state::<Mutex<BackendState>>();
+            let previous = {
+                let mut state_guard = lock_state(&state);
+                state_guard.started_at = Some(Instant::now());
+                state_guard.last_failure = None;
+                state_guard.watchdog.take()
+            };
+            if let Some(watchdog) = previous {
+                watchdog.stop();
+            }
+            let watchdog = watchdog::Watchdog::spawn(app.clone());
+            let warnings = backend::health_warnings(port).unwrap_or_default();
+            {
+                let mut state_guard = lock_state(&state);
+                state_guard.watchdog = Some(watchdog);
+                state_guard.warnings = warnings.clone();
+                state_guard.health_latencies.clear();
+            }
+            if warnings.is_empty() {
+                set_status_title(&app, None);
+                let _ = app.emit_all("backend-ready", port);
+            } else {
+                let msg = format!("[Warning] Backend running degraded: {}", warnings.join("; "));
+                eprintln!("{}", msg);
+                log_line(&app, &msg);
+                set_status_title(&app, Some("Limited Mode"));
+                let _ = app.emit_all("backend-degraded", BackendDegraded { port, warnings });
+            }
+            show_main_window(&app);
+
+            match compare_versions(port) {
+                Ok(info) if !info.matches => {
+                    let msg = format!(
+                        "[Warning] Backend version {} does not match app version {}",
+                        info.backend_version, info.app_version
+                    );
+                    eprintln!("{}", msg);
+                    log_line(&app, &msg);
+                    let _ = app.emit_all("version-mismatch", info);
+                }
+                Ok(_) => {}
+                Err(e) => log_line(&app, &format!("[Warning] Could not check backend version: {e}")),
+            }
+
+            // Externally managed backends bind wherever their owner chose, and
+            // a socket-only backend has no TCP listener to expose.
+            let exposed = if external_backend() || transport::socket_path().is_some() {
+                None
+            } else {
+                exposed_lan_address(port)
+            };
+            if let Some(ip) = exposed {
+                let msg = format!(
+                    "[Warning] Backend is reachable from the network at {}:{}",
+                    ip, port
+                );
+                eprintln!("{}", msg);
+                log_line(&app, &msg);
+                let _ = app.emit_all("backend-exposed", ip.to_string());
+            }
+        } else if let Err(e) = outcome {
+            let message = if let WaitError::PortNeverOpened(_) = e {
+                format!("Backend never started listening on port {}", port)
+            } else {
+                format!("Backend is listening on port {} but never became healthy", port)
+            };
+            eprintln!("[Warning] {}", message);
+            log_line(&app, &format!("[Warning] {}", message));
+            write_startup_report(&app, "timeout", Some(message.clone()), Some(e.elapsed()));
+            report_failure(
+                &app,
+                "backend-failed",
+                BackendFailure {
+                    message,
+                    exit_code: last_exit_code(),
+                    reason: Some(e.kind()),
+                },
+            );
+        }
+    });
+}
+
+#[derive(Serialize)]
+struct HeadlessResult {
+    ok: bool,
+    port: u16,
+    elapsed_ms: Option<u64>,
+    reason: Option<&'static str>,
+    error: Option<String>,
+}
 
-            match start_backend(&app.handle()) {
-                Ok(child) => {
+/// Spawns the backend, waits for it, prints a `HeadlessResult` line as JSON
+/// and exits 0 or 1 after stopping the backend again. Used by CI smoke tests.
+fn run_headless(app: tauri::AppHandle, port: u16) {
+    thread::spawn(move || {
+        let spawned = if external_backend() {
+            Ok((None, port))
+        } else {
+            spawn_backend(&app, port)
+        };
+        let result = match spawned {
+            Ok((child, port)) => {
+                {
                     let state = app.state::<Mutex<BackendState>>();
-                    state.lock().unwrap().child = Some(child);
-                    if wait_for_backend(30) {
-                        println!("================================================");
-                        println!("  PhotoSense-AI Ready!");
-                        println!("  Backend: http://127.0.0.1:8000");
-                        println!("================================================");
-                    } else {
-                        eprintln!("[Warning] Backend may still be starting...");
-                        log_line(&app.handle(), "[Warning] Backend may still be starting...");
-                    }
+                    let mut state_guard = lock_state(&state);
+                    backend::track_pid(child.as_ref().map(|c| c.id()));
+                    state_guard.child = child;
+                    state_guard.port = port;
                 }
-                Err(e) => {
-                    eprintln!("================================================");
-                    eprintln!("  Backend failed to start: {}", e);
-                    eprintln!("  ");
-                    eprintln!("  For development, run manually:");
-                    eprintln!("  python run_api.py");
-                    eprintln!("================================================");
-                    log_line(&app.handle(), &format!("[Error] Backend failed to start: {e}"));
+                match BackendWaiter::new(startup_timeout()).wait(port, |_| {}) {
+                    Ok(ready) => HeadlessResult {
+                        ok: true,
+                        port,
+                        elapsed_ms: Some(ready.elapsed.as_millis() as u64),
+                        reason: None,
+                        error: None,
+                    },
+                    Err(e) => HeadlessResult {
+                        ok: false,
+                        port,
+                        elapsed_ms: Some(e.elapsed().as_millis() as u64),
+                        reason: Some(e.kind()),
+                        error: Some(format!("Backend did not become ready ({})", e.kind())),
+                    },
                 }
             }
-            Ok(())
-        })
-        .on_window_event(|event| {
-            if let tauri::WindowEvent::CloseRequested { .. } = event.event() {
-                let app_handle = event.window().app_handle();
-                let state = app_handle.state::<Mutex<BackendState>>();
-                let child_opt = {
-                    let mut state_guard = state.lock().unwrap();
-                    state_guard.child.take()
-                };
-                if let Some(mut child) = child_opt {
-                    println!("[PhotoSense] Stopping backend...");
-                    let _ = child.kill();
+            Err(e) => HeadlessResult {
+                ok: false,
+                port,
+                elapsed_ms: None,
+                reason: Some("spawn_failed"),
+                error: Some(e),
+            },
+        };
+        log_line(&app, &format!("[PhotoSense] Headless run finished, ok: {}", result.ok));
+        match serde_json::to_string(&result) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("[Error] {}", e),
+        }
+        cleanup_backend(&app);
+        app.exit(if result.ok { 0 } else { 1 });
+    });
+}
+
+/// Writes `startup-report.json` next to the logs once per launch, at the
+/// first ready or failed outcome.
+fn write_startup_report(
+    app: &tauri::AppHandle,
+    status: &'static str,
+    message: Option<String>,
+    elapsed: Option<Duration>,
+) {
+    if STARTUP_REPORTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let report = {
+        let state = app.state::<Mutex<BackendState>>();
+        let state_guard = lock_state(&state);
+        StartupReport {
+            status,
+            message,
+            port: state_guard.port,
+            backend_path: state_guard
+                .backend_path
+                .as_ref()
+                .map(|p| p.to_string_lossy().to_string()),
+            spawn_attempts: state_guard.spawn_attempts,
+            elapsed_ms: elapsed.map(|d| d.as_millis() as u64),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            app_version: app.package_info().version.to_string(),
+        }
+    };
+    let data_dir = data_dir(app);
+    let _ = fs::create_dir_all(&data_dir);
+    let result = serde_json::to_vec_pretty(&report)
+        .map_err(|e| e.to_string())
+        .and_then(|json| {
+            fs::write(data_dir.join("startup-report.json"), json).map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        log_line(app, &format!("[Warning] Failed to write startup report: {e}"));
+    }
+}
+
+/// Remembers the failure so windows that load after the event fired (the
+/// splash screen) can still show it, then broadcasts it.
+fn report_failure(app: &tauri::AppHandle, event: &str, failure: BackendFailure) {
+    lock_state(&app.state::<Mutex<BackendState>>()).last_failure = Some(failure.clone());
+    set_status_title(app, Some("Backend Offline"));
+    let _ = app.emit_all(event, failure);
+}
+
+/// Shows backend status in the main window title (and so the taskbar or
+/// dock), e.g. "PhotoSense-AI — Backend Offline". `None` restores the plain
+/// title. Disabled with `PHOTOSENSE_STATUS_TITLE=0`.
+fn set_status_title(app: &tauri::AppHandle, status: Option<&str>) {
+    if !backend::status_title_enabled() {
+        return;
+    }
+    let title = match status {
+        Some(status) => format!("{} — {}", APP_TITLE, status),
+        None => APP_TITLE.to_string(),
+    };
+    if let Some(window) = app.get_window("main") {
+        let _ = window.set_title(&title);
+    }
+}
+
+fn show_main_window(app: &tauri::AppHandle) {
+    if let Some(splash) = app.get_window(SPLASH_WINDOW) {
+        let _ = splash.close();
+    }
+    if let Some(window) = app.get_window("main") {
+        let _ = window.show();
+        if backend::focus_on_ready() {
+            bring_to_front(&window);
+        }
+    }
+}
+
+/// Polls `port` for at most `PRE_SHOW_PROBE_MS`. Only for paths that show
+/// the window without waiting for `backend-ready`.
+fn probe_port_before_show(port: u16) -> bool {
+    let deadline = Instant::now() + Duration::from_millis(PRE_SHOW_PROBE_MS);
+    loop {
+        if is_port_open(port) {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Watches for the main window's frontend to report in. If it never does
+/// (a broken build or missing assets) the window would sit blank while the
+/// backend runs fine, so a native dialog says which half failed and offers
+/// a reload.
+fn watch_frontend_load(app: tauri::AppHandle) {
+    thread::spawn(move || loop {
+        // Only release builds embed the assets; dev builds load them from
+        // the dev server, which the timeout covers.
+        let missing = cfg!(feature = "custom-protocol")
+            && app.asset_resolver().get("index.html".to_string()).is_none();
+        if !missing {
+            let deadline = Instant::now() + Duration::from_secs(FRONTEND_LOAD_TIMEOUT_SECS);
+            while Instant::now() < deadline {
+                if FRONTEND_READY.load(Ordering::SeqCst) || SHUTTING_DOWN.load(Ordering::SeqCst) {
+                    return;
                 }
+                thread::sleep(Duration::from_millis(250));
             }
-        })
-        .invoke_handler(tauri::generate_handler![check_backend_status])
-        .run(tauri::generate_context!())
-        .expect("Error running PhotoSense-AI");
+        }
+
+        let port = lock_state(&app.state::<Mutex<BackendState>>()).port;
+        let backend_note = if cached_health(port, false).healthy {
+            "The photo service is running normally; only the window failed."
+        } else {
+            "This is separate from the photo service, which is still starting or has stopped."
+        };
+        let problem = if missing {
+            "The app's interface files are missing from this install. Reinstalling PhotoSense-AI should fix this."
+        } else {
+            "The PhotoSense-AI interface did not finish loading."
+        };
+        let msg = format!(
+            "[Error] Frontend failed to load ({})",
+            if missing { "index.html missing" } else { "timed out" }
+        );
+        eprintln!("{}", msg);
+        log_line(&app, &msg);
+        let _ = app.emit_all("frontend-load-failed", missing);
+
+        let reload = tauri::api::dialog::blocking::MessageDialogBuilder::new(
+            APP_TITLE,
+            format!("{}\n\n{}", problem, backend_note),
+        )
+        .kind(tauri::api::dialog::MessageDialogKind::Error)
+        .buttons(tauri::api::dialog::MessageDialogButtons::OkCancelWithLabels(
+            "Reload".to_string(),
+            "Quit".to_string(),
+        ))
+        .show();
+        if !reload {
+            app.exit(1);
+            return;
+        }
+        log_line(&app, "[PhotoSense] Reloading frontend");
+        if let Some(window) = app.get_window("main") {
+            let _ = window.eval("window.location.reload()");
+        }
+    });
+}
+
+/// Restores, shows and focuses `window`. Windows only lets the foreground
+/// app take focus, so the window is briefly made topmost to get in front
+/// and the taskbar entry flashes if focus was still refused.
+fn bring_to_front(window: &tauri::Window) {
+    if window.is_minimized().unwrap_or(false) {
+        let _ = window.unminimize();
+    }
+    let _ = window.show();
+    #[cfg(target_os = "windows")]
+    {
+        let _ = window.set_always_on_top(true);
+        let _ = window.set_always_on_top(false);
+    }
+    // On macOS this also activates the app, which focusing alone would not.
+    let _ = window.set_focus();
+    if !window.is_focused().unwrap_or(true) {
+        let _ = window.request_user_attention(Some(tauri::UserAttentionType::Informational));
+    }
+}
+
+fn compare_versions(port: u16) -> Result<VersionInfo, String> {
+    let app_version = env!("CARGO_PKG_VERSION").to_string();
+    let backend_version = backend::backend_version(port)?;
+    Ok(VersionInfo {
+        matches: app_version == backend_version,
+        app_version,
+        backend_version,
+    })
+}
+
+fn restart_after_crash(app: &tauri::AppHandle) -> bool {
+    let state = app.state::<Mutex<BackendState>>();
+    let max_attempts = max_restart_attempts();
+    if max_attempts == 0 {
+        // Restarts are off, so this is a crash rather than retries running out.
+        let exit_code = last_exit_code();
+        let msg = match exit_code {
+            Some(code) => format!("[Error] Backend crashed with exit code {}", code),
+            None => "[Error] Backend crashed".to_string(),
+        };
+        eprintln!("{}", msg);
+        log_line(app, &msg);
+        report_failure(
+            app,
+            "backend-failed",
+            BackendFailure {
+                message: "Backend crashed".to_string(),
+                exit_code,
+                reason: None,
+            },
+        );
+        return false;
+    }
+    loop {
+        let (attempt, port, crash_loop) = {
+            let mut state_guard = lock_state(&state);
+            state_guard.restart_count += 1;
+            // The buffer holds the last CRASH_LOOP_MAX_RESTARTS restarts; if
+            // the oldest is still inside the window, this one is one too many.
+            let now = Instant::now();
+            let window = Duration::from_secs(CRASH_LOOP_WINDOW_SECS);
+            let recent = &mut state_guard.recent_restarts;
+            let crash_loop = recent.len() == CRASH_LOOP_MAX_RESTARTS
+                && recent.front().is_some_and(|t| now.duration_since(*t) < window);
+            if !crash_loop {
+                if recent.len() == CRASH_LOOP_MAX_RESTARTS {
+                    recent.pop_front();
+                }
+                recent.push_back(now);
+            }
+            (state_guard.restart_count, state_guard.port, crash_loop)
+        };
+        if crash_loop {
+            let msg = format!(
+                "[Error] Backend restarted {} times within {}s, not restarting again",
+                CRASH_LOOP_MAX_RESTARTS, CRASH_LOOP_WINDOW_SECS
+            );
+            eprintln!("{}", msg);
+            log_line(app, &msg);
+            report_failure(
+                app,
+                "backend-crash-loop",
+                BackendFailure {
+                    message: "Backend keeps crashing".to_string(),
+                    exit_code: last_exit_code(),
+                    reason: None,
+                },
+            );
+            return false;
+        }
+        if attempt > max_attempts {
+            let msg = format!("[Error] Backend crashed {} times, giving up", max_attempts);
+            eprintln!("{}", msg);
+            log_line(app, &msg);
+            report_failure(
+                app,
+                "backend-crashed",
+                BackendFailure {
+                    message: format!("Backend crashed {} times", max_attempts),
+                    exit_code: last_exit_code(),
+                    reason: None,
+                },
+            );
+            return false;
+        }
+
+        let delay = restart_backoff(attempt);
+        let msg = format!(
+            "[PhotoSense] Restarting backend in {}ms (attempt {}/{})",
+            delay.as_millis(),
+            attempt,
+            max_attempts
+        );
+        println!("{}", msg);
+        log_line(app, &msg);
+        thread::sleep(delay);
+        if SHUTTING_DOWN.load(Ordering::SeqCst) {
+            return false;
+        }
+
+        match spawn_backend(app, port) {
+            Ok((child, port)) => {
+                {
+                    let mut state_guard = lock_state(&state);
+                    backend::track_pid(child.as_ref().map(|c| c.id()));
+                    state_guard.child = child;
+                    state_guard.port = port;
+                    state_guard.total_restarts += 1;
+                }
+                record_metric(app, metrics::MetricEvent::Restart { attempt });
+                announce_when_ready(app.clone(), port);
+                return true;
+            }
+            Err(e) => {
+                eprintln!("[Error] Backend restart failed: {}", e);
+                log_line(app, &format!("[Error] Backend restart failed: {e}"));
+            }
+        }
+    }
+}
+
+fn supervise_backend(app: tauri::AppHandle) {
+    if SUPERVISOR_ACTIVE.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    thread::spawn(move || {
+        supervise_loop(&app);
+        SUPERVISOR_ACTIVE.store(false, Ordering::SeqCst);
+    });
+}
+
+fn supervise_loop(app: &tauri::AppHandle) {
+    let state = app.state::<Mutex<BackendState>>();
+    let mut running_since = Instant::now();
+    loop {
+        thread::sleep(Duration::from_millis(500));
+        if SHUTTING_DOWN.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let (status, port, restart_count, watchdog) = {
+            let mut state_guard = lock_state(&state);
+            let status = match state_guard.child.as_mut() {
+                Some(child) => child.try_wait().ok().flatten(),
+                None => continue,
+            };
+            let mut watchdog = None;
+            if status.is_some() {
+                backend::track_pid(None);
+                state_guard.child = None;
+                state_guard.started_at = None;
+                state_guard.warnings.clear();
+                watchdog = state_guard.watchdog.take();
+            }
+            (status, state_guard.port, state_guard.restart_count, watchdog)
+        };
+        if let Some(watchdog) = watchdog {
+            watchdog.stop();
+        }
+
+        let status = match status {
+            Some(status) => status,
+            None => {
+                if restart_count > 0
+                    && running_since.elapsed() >= Duration::from_secs(HEALTHY_RESET_SECS)
+                    && is_port_open(port)
+                {
+                    lock_state(&state).restart_count = 0;
+                }
+                continue;
+            }
+        };
+
+        record_exit_code(status.code());
+        if SHUTTING_DOWN.load(Ordering::SeqCst) {
+            println!("[PhotoSense] Backend stopped during shutdown ({})", status);
+            return;
+        }
+        let msg = format!("[PhotoSense] Backend exited on its own with {}", status);
+        println!("{}", msg);
+        log_line(app, &msg);
+        if status.success() {
+            continue;
+        }
+        save_crash_report(app, status.code());
+        record_metric(app, metrics::MetricEvent::Crash { exit_code: status.code() });
+        if !restart_after_crash(app) {
+            return;
+        }
+        running_since = Instant::now();
+    }
+}
+
+/// Appends to the local metrics file when `PHOTOSENSE_METRICS=1`.
+fn record_metric(app: &tauri::AppHandle, event: metrics::MetricEvent) {
+    if let Some(recorder) = metrics::MetricsRecorder::new(&data_dir(app)) {
+        if let Err(e) = recorder.record(event) {
+            log_line(app, &format!("[Warning] {}", e));
+        }
+    }
+}
+
+fn save_crash_report(app: &tauri::AppHandle, exit_code: Option<i32>) {
+    let report_dir = data_dir(app);
+    let version = app.package_info().version.to_string();
+    match crash::write_report(&report_dir, &log_file(app), exit_code, &version) {
+        Ok(path) => {
+            let msg = format!("[PhotoSense] Crash report saved to {}", path.display());
+            println!("{}", msg);
+            log_line(app, &msg);
+            let _ = app.emit_all("crash-report-ready", path.to_string_lossy().to_string());
+        }
+        Err(e) => {
+            let msg = format!("[Error] Failed to write crash report: {e}");
+            eprintln!("{}", msg);
+            log_line(app, &msg);
+        }
+    }
+}
+
+/// Locks the state even if a panicking thread poisoned it, so shutdown paths
+/// can still stop the backend instead of panicking themselves.
+fn lock_state(state: &Mutex<BackendState>) -> MutexGuard<'_, BackendState> {
+    state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn cleanup_backend(app: &tauri::AppHandle) {
+    cleanup_once(&app.state::<Mutex<BackendState>>());
+}
+
+/// Runs `shutdown_backend` the first time only: closing the window, the tray
+/// Quit item and exit can each get here. Returns whether it ran.
+fn cleanup_once(state: &Mutex<BackendState>) -> bool {
+    CLEANUP.run_once(|| {
+        SHUTTING_DOWN.store(true, Ordering::SeqCst);
+        shutdown_backend(state);
+    })
+}
+
+/// The cleanup sequence itself: stops the watchdog and the backend we
+/// spawned. `cleanup_backend` runs it once per process.
+fn shutdown_backend(state: &Mutex<BackendState>) {
+    let (child_opt, port, watchdog) = {
+        let mut state_guard = lock_state(state);
+        (
+            state_guard.child.take(),
+            state_guard.port,
+            state_guard.watchdog.take(),
+        )
+    };
+    if let Some(watchdog) = watchdog {
+        watchdog.stop();
+    }
+    if let Some(mut child) = child_opt {
+        println!("[PhotoSense] Stopping backend...");
+        stop_child(&mut child, port, graceful_shutdown_timeout());
+        backend::track_pid(None);
+    }
+}
+
+/// Stops the watchdog and the backend we spawned, leaving the app running.
+/// Returns the port the backend was on.
+fn stop_tracked_backend(app: &tauri::AppHandle, message: &str) -> u16 {
+    let state = app.state::<Mutex<BackendState>>();
+    let (child_opt, port, watchdog) = {
+        let mut state_guard = lock_state(&state);
+        state_guard.started_at = None;
+        state_guard.warnings.clear();
+        (
+            state_guard.child.take(),
+            state_guard.port,
+            state_guard.watchdog.take(),
+        )
+    };
+    if let Some(watchdog) = watchdog {
+        watchdog.stop();
+    }
+    if let Some(mut child) = child_opt {
+        println!("{}", message);
+        stop_child(&mut child, port, graceful_shutdown_timeout());
+        backend::track_pid(None);
+        http_pool::clear();
+    }
+    port
+}
+
+fn restart_backend_blocking(app: &tauri::AppHandle) -> Result<u16, String> {
+    if external_backend() {
+        return Err("Backend is managed externally; restart it yourself".to_string());
+    }
+    let port = stop_tracked_backend(app, "[PhotoSense] Stopping backend for restart...");
+    spawn_and_track(app, port)
+}
+
+/// Spawns (or attaches to) a backend on `port`, records it in the state and
+/// starts readiness announcement and crash supervision.
+fn spawn_and_track(app: &tauri::AppHandle, port: u16) -> Result<u16, String> {
+    let (child, port) = spawn_backend(app, port)?;
+    {
+        let state = app.state::<Mutex<BackendState>>();
+        let mut state_guard = lock_state(&state);
+        backend::track_pid(child.as_ref().map(|c| c.id()));
+        state_guard.child = child;
+        state_guard.port = port;
+        state_guard.restart_count = 0;
+    }
+    announce_when_ready(app.clone(), port);
+    supervise_backend(app.clone());
+    Ok(port)
+}
+
+fn reveal_path(path: &std::path::Path) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    let opener = "open";
+    #[cfg(target_os = "windows")]
+    let opener = "explorer";
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let opener = "xdg-open";
+
+    Command::new(opener)
+        .arg(path)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open {}: {e}", path.display()))
+}
+
+/// Opens the backend's Swagger UI in the default browser, for poking at the
+/// API directly.
+#[tauri::command]
+fn open_backend_docs(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Mutex<BackendState>>,
+) -> Result<(), String> {
+    if transport::socket_path().is_some() {
+        return Err("API docs need TCP; the backend is listening on a Unix socket".to_string());
+    }
+    let port = lock_state(&state).port;
+    tauri::api::shell::open(&app.shell_scope(), backend::backend_url(port, "/docs"), None)
+        .map_err(|e| format!("Failed to open backend docs: {e}"))
+}
+
+/// One of the `backend::proxy_concurrency()` slots for proxied requests,
+/// released on drop.
+struct ProxySlot;
+
+impl ProxySlot {
+    fn acquire() -> Result<ProxySlot, String> {
+        let limit = backend::proxy_concurrency();
+        let timeout = Duration::from_secs(PROXY_QUEUE_TIMEOUT_SECS);
+        let in_flight = PROXY_IN_FLIGHT.lock().unwrap_or_else(PoisonError::into_inner);
+        let (mut in_flight, _) = PROXY_SLOT_FREED
+            .wait_timeout_while(in_flight, timeout, |n| *n >= limit)
+            .unwrap_or_else(PoisonError::into_inner);
+        if *in_flight >= limit {
+            return Err(format!(
+                "Backend busy: {} requests still in flight after {}s",
+                limit, PROXY_QUEUE_TIMEOUT_SECS
+            ));
+        }
+        *in_flight += 1;
+        Ok(ProxySlot)
+    }
+}
+
+impl Drop for ProxySlot {
+    fn drop(&mut self) {
+        *PROXY_IN_FLIGHT.lock().unwrap_or_else(PoisonError::into_inner) -= 1;
+        PROXY_SLOT_FREED.notify_one();
+    }
+}
+
+/// Forwards an API call to the backend with the auth token attached. The
+/// frontend uses this under Tauri, and must when the backend listens on a
+/// Unix socket, which the webview can't reach. Bodies are JSON text.
+#[tauri::command]
+async fn proxy_backend(
+    app: tauri::AppHandle,
+    method: String,
+    path: String,
+    body: Option<String>,
+) -> Result<ProxyResponse, String> {
+    let resp = proxy_blocking(app, method, path, body).await?;
+    Ok(ProxyResponse {
+        status: resp.status,
+        body: String::from_utf8_lossy(&resp.body).to_string(),
+    })
+}
+
+/// GETs an image such as a face thumbnail through the proxy, for when the
+/// webview can't load it from a URL.
+#[tauri::command]
+async fn proxy_backend_image(app: tauri::AppHandle, path: String) -> Result<Vec<u8>, String> {
+    let resp = proxy_blocking(app, "GET".to_string(), path.clone(), None).await?;
+    if !(200..300).contains(&resp.status) {
+        return Err(format!("Backend returned {} for {}", resp.status, path));
+    }
+    Ok(resp.body)
+}
+
+/// Runs `proxy_send` on the blocking pool: waiting for a slot and the
+/// request itself both block, and must not hold up the async runtime.
+async fn proxy_blocking(
+    app: tauri::AppHandle,
+    method: String,
+    path: String,
+    body: Option<String>,
+) -> Result<backend::BytesResponse, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        proxy_send(&app, &method, &path, body.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Proxy task failed: {e}"))?
+}
+
+/// Idempotent requests are retried when the backend could not be reached or
+/// dropped the connection, picking up the new port if it restarted meanwhile;
+/// bad responses and error statuses are returned, not retried. At most
+/// `PHOTOSENSE_PROXY_CONCURRENCY` requests are in flight at once; the rest
+/// queue for up to `PROXY_QUEUE_TIMEOUT_SECS`.
+fn proxy_send(
+    app: &tauri::AppHandle,
+    method: &str,
+    path: &str,
+    body: Option<&str>,
+) -> Result<backend::BytesResponse, String> {
+    let method = method.trim().to_uppercase();
+    if !PROXY_METHODS.contains(&method.as_str()) {
+        return Err(format!("Unsupported method '{}'", method));
+    }
+    // The path is written straight into the request line.
+    if !path.starts_with('/') || path.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(format!("Invalid request path '{}'", path));
+    }
+    if body.is_some_and(|body| body.len() > PROXY_MAX_BODY_BYTES) {
+        return Err(format!(
+            "Request body exceeds {} MB",
+            PROXY_MAX_BODY_BYTES / (1024 * 1024)
+        ));
+    }
+    let attempts = if PROXY_RETRY_METHODS.contains(&method.as_str()) {
+        PROXY_ATTEMPTS
+    } else {
+        1
+    };
+    let _slot = ProxySlot::acquire()?;
+    let state = app.state::<Mutex<BackendState>>();
+    let timeout = Duration::from_secs(PROXY_TIMEOUT_SECS);
+    let mut attempt = 1;
+    loop {
+        let port = lock_state(&state).port;
+        match backend::http_request_bytes(port, &method, path, body, timeout) {
+            Ok(resp) => return Ok(resp),
+            Err(e)
+                if e.is_retryable()
+                    && attempt < attempts
+                    && !SHUTTING_DOWN.load(Ordering::SeqCst) =>
+            {
+                let msg = format!("[PhotoSense] {} {} failed ({}), retrying", method, path, e);
+                println!("{}", msg);
+                log_line(app, &msg);
+                thread::sleep(Duration::from_millis(PROXY_RETRY_DELAY_MS));
+                attempt += 1;
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+}
+
+/// Answers `foreign-backend-detected`: "connect" keeps using the other
+/// install's backend, "new_port" starts our own on a free port and leaves
+/// the foreign one alone. Returns the port in use afterwards.
+#[tauri::command]
+async fn resolve_foreign_backend(app: tauri::AppHandle, action: String) -> Result<u16, String> {
+    let state = app.state::<Mutex<BackendState>>();
+    let foreign = lock_state(&state)
+        .foreign_backend
+        .take()
+        .ok_or("No foreign backend is in use")?;
+    match action.trim() {
+        "connect" => {
+            log_line(&app, "[PhotoSense] Keeping the foreign backend on request");
+            Ok(foreign.port)
+        }
+        "new_port" => {
+            if external_backend() || transport::socket_path().is_some() {
+                lock_state(&state).foreign_backend = Some(foreign);
+                return Err("Can't start a separate backend in this mode".to_string());
+            }
+            // Until our own backend is up the user can still fall back to
+            // "connect", so put the record back on failure.
+            let Some(port) = find_free_port() else {
+                lock_state(&state).foreign_backend = Some(foreign);
+                return Err("Failed to find a free port for the backend".to_string());
+            };
+            log_line(
+                &app,
+                &format!("[PhotoSense] Starting our own backend on port {} instead", port),
+            );
+            let result = spawn_and_track(&app, port);
+            if result.is_err() {
+                lock_state(&state).foreign_backend = Some(foreign);
+            }
+            result
+        }
+        other => {
+            lock_state(&state).foreign_backend = Some(foreign);
+            Err(format!("Unknown action '{}'", other))
+        }
+    }
+}
+
+/// Backend processes that are neither the one we spawned nor the one
+/// serving our port, typically left behind by a crash.
+fn orphan_backends(app: &tauri::AppHandle) -> Vec<BackendProcess> {
+    let processes = backend::find_backend_processes(&[BACKEND_BINARY, BACKEND_BINARY_ALT]);
+    let port = lock_state(&app.state::<Mutex<BackendState>>()).port;
+    let mut ours: Vec<u32> = backend::tracked_pid().into_iter().collect();
+    ours.extend(backend::pids_on_port(port));
+    // A bundled backend is a bootloader running the real server as its
+    // child, so every process chained to a live one counts as ours too.
+    loop {
+        let known = ours.len();
+        for process in &processes {
+            let chained = ours.contains(&process.parent_pid)
+                || processes
+                    .iter()
+                    .any(|child| child.parent_pid == process.pid && ours.contains(&child.pid));
+            if chained && !ours.contains(&process.pid) {
+                ours.push(process.pid);
+            }
+        }
+        if ours.len() == known {
+            break;
+        }
+    }
+    processes
+        .into_iter()
+        .filter(|process| !ours.contains(&process.pid))
+        .collect()
+}
+
+#[tauri::command]
+async fn list_orphan_backends(app: tauri::AppHandle) -> Vec<BackendProcess> {
+    orphan_backends(&app)
+}
+
+/// Kills the processes `list_orphan_backends` reports and returns their PIDs.
+#[tauri::command]
+async fn kill_orphan_backends(app: tauri::AppHandle) -> Vec<u32> {
+    let pids: Vec<u32> = orphan_backends(&app).iter().map(|process| process.pid).collect();
+    for pid in &pids {
+        backend::kill_pid(*pid);
+    }
+    if !pids.is_empty() {
+        let msg = format!("[PhotoSense] Killed stray backend processes: {:?}", pids);
+        println!("{}", msg);
+        log_line(&app, &msg);
+    }
+    pids
+}
+
+/// Called by the frontend once it has mounted.
+#[tauri::command]
+fn frontend_ready(app: tauri::AppHandle) {
+    if !FRONTEND_READY.swap(true, Ordering::SeqCst) {
+        log_line(&app, "[PhotoSense] Frontend loaded");
+    }
+}
+
+#[tauri::command]
+async fn check_data_dir(app: tauri::AppHandle) -> DataDirCheck {
+    check_data_dir_writable(&app)
+}
+
+#[tauri::command]
+async fn restart_backend(app: tauri::AppHandle) -> Result<u16, String> {
+    run_blocking(move || restart_backend_blocking(&app)).await?
+}
+
+/// Runs `f` on the blocking pool, for commands that wait on the backend or
+/// on processes and must not hold up the async runtime.
+async fn run_blocking<T, F>(f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(f)
+        .await
+        .map_err(|e| format!("Background task failed: {e}"))
+}
+
+#[tauri::command]
+async fn rebuild_index(app: tauri::AppHandle) -> Result<(), String> {
+    run_blocking(move || rebuild_index_blocking(&app)).await?
+}
+
+fn rebuild_index_blocking(app: &tauri::AppHandle) -> Result<(), String> {
+    if REBUILD_IN_PROGRESS
+        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        return Err("An index rebuild is already running".to_string());
+    }
+    let port = lock_state(&app.state::<Mutex<BackendState>>()).port;
+
+    println!("[PhotoSense] Rebuilding face index...");
+    log_line(app, "[PhotoSense] Rebuilding face index...");
+    let _ = app.emit_all("index-rebuild-progress", "started");
+    let result = backend::http_request(
+        port,
+        "POST",
+        "/faces/rebuild-index",
+        Duration::from_secs(REBUILD_INDEX_TIMEOUT_SECS),
+    )
+    .and_then(|resp| {
+        if resp.status == 200 {
+            return Ok(());
+        }
+        let detail = serde_json::from_str::<serde_json::Value>(&resp.body)
+            .ok()
+            .and_then(|json| json.get("detail")?.as_str().map(str::to_string))
+            .unwrap_or(resp.body);
+        Err(format!("Index rebuild failed ({}): {}", resp.status, detail))
+    });
+    REBUILD_IN_PROGRESS.store(false, Ordering::SeqCst);
+
+    match &result {
+        Ok(()) => {
+            log_line(app, "[PhotoSense] Face index rebuilt");
+            let _ = app.emit_all("index-rebuild-progress", "finished");
+        }
+        Err(e) => {
+            eprintln!("[Error] {}", e);
+            log_line(app, &format!("[Error] {}", e));
+            let _ = app.emit_all("index-rebuild-progress", "failed");
+        }
+    }
+    result
+}
+
+fn job_status(port: u16, job_id: &str) -> Result<Option<String>, String> {
+    let path = format!("/scan/status/{}", job_id);
+    let resp = backend::http_request(port, "GET", &path, health_timeout())?;
+    if resp.status == 404 {
+        return Ok(None);
+    }
+    if resp.status != 200 {
+        return Err(format!("Job status returned {}", resp.status));
+    }
+    Ok(serde_json::from_str::<serde_json::Value>(&resp.body)
+        .ok()
+        .and_then(|json| json.get("status")?.as_str().map(str::to_string)))
+}
+
+/// Asks the backend to cancel a scan job and waits for it to stop. A job that
+/// already finished, or that the backend doesn't know, is reported as not
+/// cancelled rather than as an error. The wait polls, so it runs on the
+/// blocking pool rather than an async runtime worker.
+#[tauri::command]
+async fn cancel_operation(app: tauri::AppHandle, job_id: String) -> Result<CancelResult, String> {
+    tauri::async_runtime::spawn_blocking(move || cancel_job(&app, &job_id))
+        .await
+        .map_err(|e| format!("Cancel task failed: {e}"))?
+}
+
+fn cancel_job(app: &tauri::AppHandle, job_id: &str) -> Result<CancelResult, String> {
+    // The id goes into the request path.
+    if job_id.is_empty() || !job_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("Invalid job id '{}'", job_id));
+    }
+    let port = lock_state(&app.state::<Mutex<BackendState>>()).port;
+    let outcome = |cancelled, status: String| CancelResult {
+        job_id: job_id.to_string(),
+        cancelled,
+        status,
+    };
+
+    let path = format!("/scan/cancel/{}", job_id);
+    let resp = backend::http_request(port, "POST", &path, health_timeout())?;
+    if resp.status == 404 {
+        return Ok(outcome(false, "unknown".to_string()));
+    }
+    if resp.status != 200 {
+        return Err(format!("Cancel request returned {}", resp.status));
+    }
+    let json = serde_json::from_str::<serde_json::Value>(&resp.body)
+        .map_err(|e| format!("Invalid cancel response: {e}"))?;
+    if json.get("cancelling").and_then(|v| v.as_bool()) != Some(true) {
+        let status = json.get("status").and_then(|v| v.as_str()).unwrap_or("unknown");
+        return Ok(outcome(false, status.to_string()));
+    }
+
+    log_line(app, &format!("[PhotoSense] Cancelling job {}", job_id));
+    let deadline = Instant::now() + Duration::from_secs(CANCEL_WAIT_SECS);
+    while Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(CANCEL_POLL_MS));
+        match job_status(port, job_id)? {
+            Some(status) if TERMINAL_JOB_STATES.contains(&status.as_str()) => {
+                log_line(app, &format!("[PhotoSense] Job {} ended as {}", job_id, status));
+                return Ok(outcome(status == "cancelled", status));
+            }
+            Some(_) => {}
+            None => return Ok(outcome(false, "unknown".to_string())),
+        }
+    }
+    Err(format!("Job {} did not stop within {}s", job_id, CANCEL_WAIT_SECS))
+}
+
+/// Runs the backend self-test, emitting `self-test-progress` every couple of
+/// seconds while it runs since a cold model load can take minutes.
+#[tauri::command]
+async fn run_backend_self_test(app: tauri::AppHandle) -> Result<SelfTestResult, String> {
+    run_blocking(move || self_test_blocking(&app)).await?
+}
+
+fn self_test_blocking(app: &tauri::AppHandle) -> Result<SelfTestResult, String> {
+    let state = app.state::<Mutex<BackendState>>();
+    if SELF_TEST_IN_PROGRESS
+        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        return Err("A self-test is already running".to_string());
+    }
+    let port = lock_state(&state).port;
+
+    log_line(app, "[PhotoSense] Running backend self-test...");
+    let started = Instant::now();
+    let progress = |stage| SelfTestProgress {
+        stage,
+        elapsed_secs: started.elapsed().as_secs(),
+    };
+    let _ = app.emit_all("self-test-progress", progress("started"));
+    let (tx, rx) = std::sync::mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(backend::run_self_test(
+            port,
+            Duration::from_secs(SELF_TEST_TIMEOUT_SECS),
+        ));
+    });
+    let result = loop {
+        match rx.recv_timeout(Duration::from_secs(SELF_TEST_PROGRESS_SECS)) {
+            Ok(result) => break result,
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+                let _ = app.emit_all("self-test-progress", progress("running"));
+            }
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                break Err("Self-test thread exited unexpectedly".to_string())
+            }
+        }
+    };
+    SELF_TEST_IN_PROGRESS.store(false, Ordering::SeqCst);
+
+    match &result {
+        Ok(report) => {
+            lock_state(&state).last_self_test = Some(report.clone());
+            let msg = format!(
+                "[PhotoSense] Self-test {} in {} ms",
+                if report.ok { "passed" } else { "found problems" },
+                started.elapsed().as_millis()
+            );
+            log_line(app, &msg);
+            let _ = app.emit_all("self-test-progress", progress("finished"));
+        }
+        Err(e) => {
+            eprintln!("[Error] Self-test failed: {}", e);
+            log_line(app, &format!("[Error] Self-test failed: {e}"));
+            let _ = app.emit_all("self-test-progress", progress("failed"));
+        }
+    }
+    result
+}
+
+#[tauri::command]
+fn get_last_self_test(state: tauri::State<'_, Mutex<BackendState>>) -> Option<SelfTestResult> {
+    lock_state(&state).last_self_test.clone()
+}
+
+#[tauri::command]
+async fn set_backend_log_level(
+    state: tauri::State<'_, Mutex<BackendState>>,
+    level: String,
+) -> Result<(), String> {
+    let parsed = logs::LogLevel::parse(&level).ok_or_else(|| {
+        format!(
+            "Unknown log level '{}'; expected one of debug, info, warn, error",
+            level
+        )
+    })?;
+    let port = lock_state(&state).port;
+    let path = format!("/log-level?level={}", parsed.as_str());
+    let resp = backend::http_request(port, "POST", &path, health_timeout())?;
+    if resp.status != 200 {
+        return Err(format!("Backend rejected log level change ({})", resp.status));
+    }
+    logs::set_forward_level(parsed);
+    Ok(())
+}
+
+/// Brings the backend's scheduling priority in line with the user's choice,
+/// or with indexing activity when there is none: low while a scan or index
+/// job runs, normal otherwise.
+fn apply_backend_priority(app: &tauri::AppHandle) -> Result<(), String> {
+    let state = app.state::<Mutex<BackendState>>();
+    let (pid, port, chosen, applied) = {
+        let state_guard = lock_state(&state);
+        (
+            state_guard.child.as_ref().map(|child| child.id()),
+            state_guard.port,
+            state_guard.priority_override,
+            state_guard.applied_priority,
+        )
+    };
+    let Some(pid) = pid else {
+        return Ok(());
+    };
+    let desired = chosen.unwrap_or_else(|| {
+        if is_indexing(port) {
+            Priority::Low
+        } else {
+            Priority::Normal
+        }
+    });
+    if applied == Some((pid, desired)) {
+        return Ok(());
+    }
+    // Recorded even on failure so the periodic check doesn't retry every tick.
+    lock_state(&state).applied_priority = Some((pid, desired));
+    // A freshly spawned backend already runs at normal priority.
+    if applied.is_none_or(|(applied_pid, _)| applied_pid != pid) && desired == Priority::Normal {
+        return Ok(());
+    }
+    backend::set_process_priority(pid, desired)?;
+    log_line(app, &format!("[PhotoSense] Backend priority set to {:?}", desired));
+    Ok(())
+}
+
+fn spawn_priority_manager(app: tauri::AppHandle) {
+    thread::spawn(move || {
+        while !SHUTTING_DOWN.load(Ordering::SeqCst) {
+            if let Err(e) = apply_backend_priority(&app) {
+                eprintln!("[Warning] {}", e);
+                log_line(&app, &format!("[Warning] {}", e));
+            }
+            thread::sleep(Duration::from_secs(PRIORITY_CHECK_INTERVAL_SECS));
+        }
+    });
+}
+
+/// `level` is "low", "normal", or "auto" to follow indexing activity again.
+#[tauri::command]
+async fn set_backend_priority(app: tauri::AppHandle, level: String) -> Result<(), String> {
+    let chosen = if level.trim().eq_ignore_ascii_case("auto") {
+        None
+    } else {
+        Some(Priority::parse(&level).ok_or_else(|| format!("Unknown priority '{}'", level))?)
+    };
+    {
+        let state = app.state::<Mutex<BackendState>>();
+        lock_state(&state).priority_override = chosen;
+    }
+    apply_backend_priority(&app)
+}
+
+/// Switches inference between "gpu", "cpu" and "auto", saves the choice to
+/// `photosense.toml` and restarts the backend so it takes effect.
+#[tauri::command]
+async fn set_backend_device(app: tauri::AppHandle, device: String) -> Result<u16, String> {
+    run_blocking(move || {
+        let chosen = Device::parse(&device).ok_or_else(|| format!("Unknown device '{}'", device))?;
+        if external_backend() {
+            return Err("Backend is managed externally; set its device yourself".to_string());
+        }
+        config::save_value(
+            &data_dir(&app),
+            "device",
+            toml::Value::String(chosen.as_str().to_string()),
+        )?;
+        lock_state(&app.state::<Mutex<BackendState>>()).device = chosen;
+        let msg = format!("[PhotoSense] Backend device set to {}, restarting", chosen.as_str());
+        println!("{}", msg);
+        log_line(&app, &msg);
+        restart_backend_blocking(&app)
+    })
+    .await?
+}
+
+/// Compares the running backend's version with the update manifest and
+/// emits `backend-update-available` when a newer build is published.
+#[tauri::command]
+async fn check_backend_update(app: tauri::AppHandle) -> Result<update::UpdateInfo, String> {
+    if external_backend() {
+        return Err("Backend is managed externally; update it yourself".to_string());
+    }
+    let port = lock_state(&app.state::<Mutex<BackendState>>()).port;
+    let app_version = app.package_info().version.to_string();
+    let info = run_blocking(move || {
+        let current = backend::backend_version(port).unwrap_or(app_version);
+        update::check(&current)
+    })
+    .await??;
+    if info.available {
+        log_line(
+            &app,
+            &format!("[PhotoSense] Backend update {} available", info.latest_version),
+        );
+        let _ = app.emit_all("backend-update-available", info.clone());
+    }
+    Ok(info)
+}
+
+/// Downloads and verifies the latest backend. It replaces the current one
+/// the next time the backend starts; returns the staged version.
+#[tauri::command]
+async fn apply_backend_update(app: tauri::AppHandle) -> Result<String, String> {
+    if external_backend() {
+        return Err("Backend is managed externally; update it yourself".to_string());
+    }
+    if UPDATE_IN_PROGRESS.swap(true, Ordering::SeqCst) {
+        return Err("A backend update is already in progress".to_string());
+    }
+    let dir = data_dir(&app);
+    // The download can take many minutes.
+    let result = run_blocking(move || update::stage(&dir)).await.and_then(|staged| staged);
+    UPDATE_IN_PROGRESS.store(false, Ordering::SeqCst);
+
+    match &result {
+        Ok(version) => {
+            let msg = format!("[PhotoSense] Backend update {} staged for next restart", version);
+            println!("{}", msg);
+            log_line(&app, &msg);
+            let _ = app.emit_all("backend-update-applied", version.clone());
+        }
+        Err(e) => {
+            eprintln!("[Error] {}", e);
+            log_line(&app, &format!("[Error] {}", e));
+        }
+    }
+    result
+}
+
+/// Stops the backend, deletes everything in the data dir (logs too unless
+/// `keep_logs`) and starts it again. Returns the number of bytes freed.
+/// `photosense.toml` holds the shell's own settings rather than library data,
+/// so it survives, and so does the migration marker, without which the next
+/// launch would copy the legacy library straight back in.
+#[tauri::command]
+async fn clear_backend_data(app: tauri::AppHandle, keep_logs: bool) -> Result<u64, String> {
+    run_blocking(move || {
+        if external_backend() {
+            return Err("Backend is managed externally; clear its data yourself".to_string());
+        }
+        if CLEAR_IN_PROGRESS.swap(true, Ordering::SeqCst) {
+            return Err("Backend data is already being cleared".to_string());
+        }
+
+        let _ = app.emit_all("backend-data-clear-progress", "stopping");
+        let port = stop_tracked_backend(&app, "[PhotoSense] Stopping backend to clear its data...");
+
+        let _ = app.emit_all("backend-data-clear-progress", "deleting");
+        let markers = [single_instance::LOCK_FILE_NAME, data_dir::MIGRATED_MARKER];
+        let mut keep = vec![
+            single_instance::LOCK_FILE_NAME,
+            data_dir::MIGRATED_MARKER,
+            config::CONFIG_FILE,
+        ];
+        if keep_logs {
+            keep.push("logs");
+        }
+        let app_data_dir = tauri::api::path::app_data_dir(&app.config());
+        let result =
+            reset::clear_data_dir(&data_dir(&app), app_data_dir.as_deref(), &markers, &keep);
+        match &result {
+            Ok(freed) => {
+                let msg = format!("[PhotoSense] Cleared backend data, freed {} bytes", freed);
+                println!("{}", msg);
+                log_line(&app, &msg);
+            }
+            Err(e) => {
+                eprintln!("[Error] {}", e);
+                log_line(&app, &format!("[Error] {}", e));
+            }
+        }
+
+        // Restart even after a failed clear so the app is not left without a
+        // backend.
+        let _ = app.emit_all("backend-data-clear-progress", "restarting");
+        let restarted = spawn_and_track(&app, port);
+        CLEAR_IN_PROGRESS.store(false, Ordering::SeqCst);
+        let freed = result?;
+        restarted?;
+        let _ = app.emit_all("backend-data-cleared", freed);
+        Ok(freed)
+    })
+    .await?
+}
+
+#[tauri::command]
+fn confirm_close(app: tauri::AppHandle) {
+    CLOSE_CONFIRMED.store(true, Ordering::SeqCst);
+    log_line(&app, "[PhotoSense] Quitting while indexing was in progress");
+    cleanup_backend(&app);
+    app.exit(0);
+}
+
+#[tauri::command]
+async fn get_backend_version(
+    state: tauri::State<'_, Mutex<BackendState>>,
+) -> Result<VersionInfo, String> {
+    let port = lock_state(&state).port;
+    compare_versions(port)
+}
+
+#[tauri::command]
+fn get_backend_failure(state: tauri::State<'_, Mutex<BackendState>>) -> Option<BackendFailure> {
+    lock_state(&state).last_failure.clone()
+}
+
+#[tauri::command]
+async fn ping_backend(
+    state: tauri::State<'_, Mutex<BackendState>>,
+    timeout_secs: u64,
+) -> Result<PingResult, String> {
+    let port = lock_state(&state).port;
+    let (min, max) = PING_TIMEOUT_RANGE;
+    let timeout = Duration::from_secs(timeout_secs.clamp(min, max));
+    let started = Instant::now();
+    let status_code = backend::http_request(port, "GET", backend::health_path(), timeout)
+        .ok()
+        .map(|resp| resp.status);
+    Ok(PingResult {
+        ok: status_code == Some(200),
+        latency_ms: started.elapsed().as_millis() as u64,
+        status_code,
+    })
+}
+
+#[tauri::command]
+fn get_backend_status(state: tauri::State<'_, Mutex<BackendState>>) -> BackendStatus {
+    let state_guard = lock_state(&state);
+    BackendStatus {
+        running: state_guard.started_at.is_some(),
+        port: state_guard.port,
+        pid: state_guard.child.as_ref().map(|child| child.id()),
+        uptime_secs: state_guard.started_at.map(|t| t.elapsed().as_secs()),
+        restart_count: state_guard.total_restarts,
+        degraded: !state_guard.warnings.is_empty(),
+        warnings: state_guard.warnings.clone(),
+        models_ready: MODELS_READY.load(Ordering::SeqCst),
+        transport: transport::name(),
+        foreign_backend: state_guard.foreign_backend.clone(),
+    }
+}
+
+/// Starts the backend if it isn't running, e.g. after `stop_backend` or when
+/// auto-start is disabled. Returns the port; `backend-ready` follows once it
+/// answers.
+#[tauri::command]
+async fn start_backend(app: tauri::AppHandle) -> Result<u16, String> {
+    run_blocking(move || {
+        if external_backend() {
+            return Err("Backend is managed externally; start it yourself".to_string());
+        }
+        let port = {
+            let state = app.state::<Mutex<BackendState>>();
+            let mut state_guard = lock_state(&state);
+            let alive = state_guard
+                .child
+                .as_mut()
+                .is_some_and(|child| matches!(child.try_wait(), Ok(None)));
+            if alive || state_guard.started_at.is_some() {
+                return Ok(state_guard.port);
+            }
+            state_guard.port
+        };
+        log_line(&app, "[PhotoSense] Starting backend on request");
+        spawn_and_track(&app, port)
+    })
+    .await?
+}
+
+/// Executable, working directory, arguments and environment of the last
+/// backend launch, or `None` if the shell hasn't launched one.
+#[tauri::command]
+fn get_backend_launch_info(state: tauri::State<'_, Mutex<BackendState>>) -> Option<LaunchInfo> {
+    let mut info = lock_state(&state).launch_info.clone()?;
+    let path = std::path::Path::new(&info.executable);
+    info.exists = path.is_file();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        info.is_executable = fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o111 != 0);
+    }
+    #[cfg(not(unix))]
+    {
+        info.is_executable = info.exists;
+    }
+    Some(info)
+}
+
+#[tauri::command]
+async fn stop_backend(app: tauri::AppHandle) -> Result<(), String> {
+    run_blocking(move || {
+        if external_backend() {
+            return Err("Backend is managed externally; stop it yourself".to_string());
+        }
+        stop_tracked_backend(&app, "[PhotoSense] Stopping backend on request...");
+        log_line(&app, "[PhotoSense] Backend stopped on request");
+        set_status_title(&app, Some("Backend Stopped"));
+        let _ = app.emit_all("backend-stopped", ());
+        Ok(())
+    })
+    .await?
+}
+
+#[tauri::command]
+async fn reset_crash_loop(app: tauri::AppHandle) -> Result<u16, String> {
+    run_blocking(move || {
+        {
+            let state = app.state::<Mutex<BackendState>>();
+            let mut state_guard = lock_state(&state);
+            state_guard.recent_restarts.clear();
+            state_guard.last_failure = None;
+        }
+        log_line(&app, "[PhotoSense] Crash loop reset, restarting backend");
+        restart_backend_blocking(&app)
+    })
+    .await?
+}
+
+#[tauri::command]
+async fn check_backend_status(
+    state: tauri::State<'_, Mutex<BackendState>>,
+) -> Result<bool, String> {
+    let port = lock_state(&state).port;
+    run_blocking(move || is_port_open(port)).await
+}
+
+#[tauri::command]
+fn get_backend_port(state: tauri::State<'_, Mutex<BackendState>>) -> u16 {
+    lock_state(&state).port
+}
+
+/// Base URL for direct HTTP calls, following the port actually in use.
+/// `None` on a Unix socket, where everything has to go through the proxy.
+#[tauri::command]
+fn get_backend_url(state: tauri::State<'_, Mutex<BackendState>>) -> Option<String> {
+    if transport::socket_path().is_some() {
+        return None;
+    }
+    Some(backend::backend_url(lock_state(&state).port, ""))
+}
+
+#[tauri::command]
+fn get_backend_token() -> String {
+    auth_token().to_string()
+}
+
+#[tauri::command]
+async fn get_backend_health(
+    state: tauri::State<'_, Mutex<BackendState>>,
+    force: Option<bool>,
+) -> Result<BackendHealth, String> {
+    let (port, pid, uptime_secs) = {
+        let state_guard = lock_state(&state);
+        (
+            state_guard.port,
+            state_guard.child.as_ref().map(|child| child.id()),
+            state_guard.started_at.map(|t| t.elapsed().as_secs()),
+        )
+    };
+
+    let force = force.unwrap_or(false);
+    let sample = run_blocking(move || cached_health(port, force)).await?;
+
+    Ok(BackendHealth {
+        port,
+        healthy: sample.healthy,
+        cached: sample.cached,
+        response_time_ms: sample.latency.as_millis() as u64,
+        pid,
+        uptime_secs,
+    })
+}
+
+#[tauri::command]
+async fn get_backend_resource_usage(
+    state: tauri::State<'_, Mutex<BackendState>>,
+) -> Result<usage::ResourceUsage, String> {
+    let pid = {
+        let state_guard = lock_state(&state);
+        state_guard.child.as_ref().map(|child| child.id()).unwrap_or(0)
+    };
+    if pid == 0 {
+        return Err("Backend is not running".to_string());
+    }
+    run_blocking(move || usage::sample(pid)).await?
+}
+
+#[tauri::command]
+fn tail_backend_log(app: tauri::AppHandle, lines: usize) -> Result<Vec<String>, String> {
+    let log_file = log_file(&app);
+    match logs::read_last_lines(&log_file, lines.min(MAX_TAIL_LINES)) {
+        Ok(tail) => Ok(tail),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("Failed to read backend log: {e}")),
+    }
+}
+
+/// Port the backend is actually listening on, taken from its uvicorn startup
+/// line. If it differs from the tracked port and a PhotoSense backend answers
+/// there, the state is corrected and `backend-ready` re-emitted.
+#[tauri::command]
+async fn get_effective_port(app: tauri::AppHandle) -> Result<EffectivePort, String> {
+    let requested = lock_state(&app.state::<Mutex<BackendState>>()).port;
+    let log_file = log_file(&app);
+    let logged = logs::read_last_lines(&log_file, MAX_TAIL_LINES)
+        .ok()
+        .and_then(|lines| logs::listening_port(&lines));
+
+    // The log may hold a line from an earlier run, so only trust it when a
+    // PhotoSense backend answers there.
+    let actual = match logged {
+        Some(port) if port != requested && is_our_backend(port) => port,
+        _ if is_our_backend(requested) => requested,
+        _ => return Err("Could not determine the backend's listening port".to_string()),
+    };
+    let corrected = actual != requested;
+    if corrected {
+        let msg = format!(
+            "[Warning] Backend is listening on port {} instead of {}, switching",
+            actual, requested
+        );
+        eprintln!("{}", msg);
+        log_line(&app, &msg);
+        lock_state(&app.state::<Mutex<BackendState>>()).port = actual;
+        if !external_backend() {
+            save_last_port(&app, actual);
+        }
+        let _ = app.emit_all("backend-ready", actual);
+    }
+    Ok(EffectivePort {
+        requested,
+        actual,
+        corrected,
+    })
+}
+
+/// Follows `backend.log` from its current end and emits appended lines as
+/// `backend-log-line` events until `stop_log_stream` is called. Starting a
+/// new stream replaces any running one.
+#[tauri::command]
+fn start_log_stream(app: tauri::AppHandle) -> Result<(), String> {
+    let log_file = log_file(&app);
+    let mut offset = fs::metadata(&log_file).map(|m| m.len()).unwrap_or(0);
+    let generation = LOG_STREAM_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+
+    thread::spawn(move || {
+        let mut partial = Vec::new();
+        while LOG_STREAM_GENERATION.load(Ordering::SeqCst) == generation
+            && !SHUTTING_DOWN.load(Ordering::SeqCst)
+        {
+            thread::sleep(Duration::from_millis(LOG_STREAM_INTERVAL_MS));
+            let mut lines = match logs::read_new_lines(&log_file, &mut offset, &mut partial) {
+                Ok(lines) => lines,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    eprintln!("[Warning] Log stream stopped: {}", e);
+                    return;
+                }
+            };
+            if lines.is_empty() {
+                continue;
+            }
+            let dropped = lines.len().saturating_sub(LOG_STREAM_MAX_BATCH);
+            lines.drain(..dropped);
+            let _ = app.emit_all("backend-log-line", BackendLogBatch { lines, dropped });
+        }
+    });
+    Ok(())
+}
+
+#[tauri::command]
+fn stop_log_stream() {
+    LOG_STREAM_GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+#[tauri::command]
+fn get_backend_logs_path(app: tauri::AppHandle) -> Result<String, String> {
+    let log_dir = log_path(&app);
+    fs::create_dir_all(&log_dir).map_err(|e| format!("Failed to create log directory: {e}"))?;
+    Ok(log_dir.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+fn open_logs_folder(app: tauri::AppHandle) -> Result<(), String> {
+    let log_dir = log_path(&app);
+    fs::create_dir_all(&log_dir).map_err(|e| format!("Failed to create log directory: {e}"))?;
+    reveal_path(&log_dir)
+}
+
+/// Counts and averages from the local metrics file, for a diagnostics view.
+#[tauri::command]
+async fn get_metrics_summary(app: tauri::AppHandle) -> metrics::MetricsSummary {
+    metrics::summarize(&data_dir(&app))
+}
+
+/// Bundles logs, startup and crash reports, config and system info into one
+/// archive for support requests and returns its path.
+#[tauri::command]
+async fn export_diagnostics(app: tauri::AppHandle) -> Result<String, String> {
+    let dir = data_dir(&app);
+    let (port, pid) = {
+        let state = app.state::<Mutex<BackendState>>();
+        let state_guard = lock_state(&state);
+        (state_guard.port, state_guard.child.as_ref().map(|child| child.id()))
+    };
+
+    let config_file = dir.join(config::CONFIG_FILE);
+    let effective = config::effective(config_file.exists().then_some(config_file.as_path()));
+    let effective_json = serde_json::to_string_pretty(&effective).map_err(|e| e.to_string())?;
+
+    let mut system_info = format!(
+        "App version: {}\nOS: {} {} ({})\nBackend port: {}\n",
+        app.package_info().version,
+        std::env::consts::OS,
+        crash::os_version(),
+        std::env::consts::ARCH,
+        port
+    );
+    match pid {
+        Some(pid) => system_info.push_str(&format!("Backend PID: {}\n", pid)),
+        None => system_info.push_str("Backend PID: none\n"),
+    }
+    match backend::backend_version(port) {
+        Ok(version) => system_info.push_str(&format!("Backend version: {}\n", version)),
+        Err(e) => system_info.push_str(&format!("Backend version: unavailable ({})\n", e)),
+    }
+    if let Ok(free) = usage::free_disk_space(&dir) {
+        system_info.push_str(&format!("Free disk space: {} MB\n", free / (1024 * 1024)));
+    }
+
+    let path = diagnostics::write_bundle(
+        &dir,
+        &log_file(&app),
+        &[
+            ("effective-config.json", effective_json),
+            ("system-info.txt", system_info),
+        ],
+    )?;
+    let msg = format!("[PhotoSense] Diagnostics exported to {}", path.display());
+    println!("{}", msg);
+    log_line(&app, &msg);
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Release builds use the "windows" subsystem and have no console, so
+/// debug output would go nowhere without one.
+#[cfg(windows)]
+fn attach_debug_console() {
+    use windows_sys::Win32::System::Console::{AllocConsole, AttachConsole, ATTACH_PARENT_PROCESS};
+
+    // SAFETY: plain Win32 calls; failing just means we already have a console.
+    unsafe {
+        if AttachConsole(ATTACH_PARENT_PROCESS) == 0 {
+            AllocConsole();
+        }
+    }
+}
+
+fn main() {
+    #[cfg(windows)]
+    if backend::debug_mode() {
+        attach_debug_console();
+    }
+
+    let headless = backend::headless();
+    let mut context = tauri::generate_context!();
+    if headless {
+        context.config_mut().tauri.windows.clear();
+    }
+    let data_dir = data_dir::resolve(context.config());
+    let instance_focus = match data_dir.as_ref() {
+        Some(data_dir) => match single_instance::acquire(data_dir) {
+            single_instance::Instance::Primary(listener) => {
+                listener.map(single_instance::serve)
+            }
+            single_instance::Instance::Secondary => {
+                if headless {
+                    eprintln!("[Error] Another PhotoSense instance is already running");
+                    std::process::exit(1);
+                }
+                println!("[PhotoSense] Already running, focusing the existing window");
+                return;
+            }
+        },
+        None => None,
+    };
+
+    // Before anything else reads the data dir, and only in the primary
+    // instance so two launches never copy at once.
+    let migration = data_dir.as_deref().map(data_dir::migrate);
+
+    let mut config_source = None;
+    let mut config_error = None;
+    if let Some(data_dir) = data_dir.as_ref() {
+        match config::load(data_dir) {
+            Ok(Some(file_config)) => {
+                config::apply_to_env(&file_config);
+                config_source = Some(data_dir.join(config::CONFIG_FILE));
+            }
+            Ok(None) => {}
+            Err(e) => config_error = Some(e),
+        }
+    }
+    if let Some(level) = logs::env_level() {
+        logs::set_forward_level(level);
+    }
+    if let Some(data_dir) = data_dir.as_ref() {
+        transport::init(data_dir);
+    }
+
+    // Without an explicit port, reuse the last one that worked so API URLs
+    // stay stable across launches, unless something else holds it now.
+    let last_port = data_dir
+        .as_deref()
+        .and_then(read_last_port)
+        .filter(|_| std::env::var_os(BACKEND_PORT_ENV).is_none());
+    let port = match last_port {
+        Some(last) if !is_port_open(last) || is_our_backend(last) => last,
+        _ => backend_port(),
+    };
+
+    backend::install_signal_handlers();
+
+    // The hook must not touch BackendState (see backend::kill_tracked_backend):
+    // the panic may have happened while its lock was held.
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        SHUTTING_DOWN.store(true, Ordering::SeqCst);
+        // A main-thread panic takes the whole app down with it, so stop the
+        // backend rather than leave it orphaned.
+        if thread::current().name() == Some("main") {
+            backend::kill_tracked_backend();
+        }
+        default_hook(info);
+    }));
+
+    let builder = tauri::Builder::default()
+        .manage(Mutex::new(BackendState::new(port)));
+    let builder = if headless {
+        builder
+    } else {
+        builder
+            .system_tray(tray::system_tray())
+            .on_system_tray_event(tray::handle_tray_event)
+    };
+    builder
+        .setup(move |app| {
+            println!("================================================");
+            println!("  PhotoSense-AI Starting");
+            println!("================================================");
+            if let Some(focus) = instance_focus {
+                focus.attach(app.handle());
+            }
+            if let Some(e) = config_error.as_ref() {
+                eprintln!("[Error] {}", e);
+                log_line(&app.handle(), &format!("[Error] {}", e));
+            }
+            match migration {
+                Some(Ok(Some(migration))) => {
+                    let msg = format!(
+                        "[PhotoSense] Migrated {} files ({} bytes) from {} ({} skipped)",
+                        migration.files, migration.bytes, migration.from, migration.skipped
+                    );
+                    println!("{}", msg);
+                    log_line(&app.handle(), &msg);
+                    let _ = app.emit_all("data-migrated", migration);
+                }
+                Some(Err(e)) => {
+                    eprintln!("[Error] {}", e);
+                    log_line(&app.handle(), &format!("[Error] {}", e));
+                }
+                _ => {}
+            }
+            let effective = config::effective(config_source.as_deref());
+            if let Some(source) = effective.source.as_ref() {
+                log_line(&app.handle(), &format!("[PhotoSense] Loaded config from {}", source));
+            }
+            let _ = app.emit_all("config-loaded", effective);
+            if std::env::var_os(LOG_FILE_ENV).is_some() {
+                let msg = format!("[PhotoSense] Logging to {}", log_file(&app.handle()).display());
+                println!("{}", msg);
+                log_line(&app.handle(), &msg);
+            }
+
+            let msg = format!(
+                "[PhotoSense] Startup timeout: {}s, health check timeout: {}s",
+                startup_timeout().as_secs(),
+                health_timeout().as_secs()
+            );
+            println!("{}", msg);
+            log_line(&app.handle(), &msg);
+
+            if headless {
+                run_headless(app.handle(), port);
+                return Ok(());
+            }
+            watch_frontend_load(app.handle());
+            if !external_backend() && !backend::auto_start() {
+                let msg = "[PhotoSense] Auto-start disabled; backend will start on request";
+                println!("{}", msg);
+                log_line(&app.handle(), msg);
+                // Nothing will announce readiness in this mode, but a backend
+                // may already be coming up on the port; give it a moment so
+                // the first render doesn't hit connection-refused errors.
+                if probe_port_before_show(port) {
+                    let msg = format!("[PhotoSense] Backend already listening on port {}", port);
+                    log_line(&app.handle(), &msg);
+                }
+                show_main_window(&app.handle());
+                tray::spawn_status_refresher(app.handle());
+                spawn_priority_manager(app.handle());
+                return Ok(());
+            }
+
+            let spawned = if external_backend() {
+                let msg = format!(
+                    "[PhotoSense] External backend mode: attaching to port {} without spawning",
+                    port
+                );
+                println!("{}", msg);
+                log_line(&app.handle(), &msg);
+                Ok((None, port))
+            } else {
+                spawn_backend(&app.handle(), port)
+            };
+            match spawned {
+                Ok((child, port)) => {
+                    let state = app.state::<Mutex<BackendState>>();
+                    {
+                        let mut state_guard = lock_state(&state);
+                        backend::track_pid(child.as_ref().map(|c| c.id()));
+                        state_guard.child = child;
+                        state_guard.port = port;
+                    }
+                    announce_when_ready(app.handle(), port);
+                    supervise_backend(app.handle());
+                }
+                Err(e) => {
+                    eprintln!("================================================");
+                    eprintln!("  Backend failed to start: {}", e);
+                    eprintln!("  ");
+                    eprintln!("  For development, run manually:");
+                    eprintln!("  python run_api.py");
+                    eprintln!("================================================");
+                    log_line(&app.handle(), &format!("[Error] Backend failed to start: {e}"));
+                    write_startup_report(&app.handle(), "spawn_failed", Some(e.clone()), None);
+                    report_failure(
+                        &app.handle(),
+                        "backend-failed",
+                        BackendFailure {
+                            message: e,
+                            exit_code: None,
+                            reason: None,
+                        },
+                    );
+                }
+            }
+            tray::spawn_status_refresher(app.handle());
+            spawn_priority_manager(app.handle());
+            Ok(())
+        })
+        .on_window_event(|event| {
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event.event() {
+                let app = event.window().app_handle();
+                if event.window().label() == SPLASH_WINDOW {
+                    // Closed by us once the backend is ready; closed by the user
+                    // before that means they gave up on startup.
+                    let main_visible = app
+                        .get_window("main")
+                        .and_then(|w| w.is_visible().ok())
+                        .unwrap_or(false);
+                    if !main_visible {
+                        cleanup_backend(&app);
+                        app.exit(0);
+                    }
+                    return;
+                }
+                if CLOSE_CONFIRMED.load(Ordering::SeqCst) {
+                    cleanup_backend(&app);
+                    return;
+                }
+                // Asking the backend is an HTTP round trip, which must not
+                // stall the event loop, so hold the close and decide off-thread.
+                api.prevent_close();
+                if CLOSE_CHECK_IN_PROGRESS.swap(true, Ordering::SeqCst) {
+                    return;
+                }
+                thread::spawn(move || {
+                    let port = lock_state(&app.state::<Mutex<BackendState>>()).port;
+                    let indexing = is_indexing(port);
+                    CLOSE_CHECK_IN_PROGRESS.store(false, Ordering::SeqCst);
+                    if indexing {
+                        let _ = app.emit_all("confirm-close-during-indexing", ());
+                    } else {
+                        cleanup_backend(&app);
+                        app.exit(0);
+                    }
+                });
+            }
+        })
+        .invoke_handler(tauri::generate_handler![
+            check_backend_status,
+            get_backend_status,
+            get_backend_port,
+            get_backend_url,
+            get_backend_token,
+            get_backend_health,
+            get_backend_version,
+            get_backend_failure,
+            ping_backend,
+            get_backend_resource_usage,
+            tail_backend_log,
+            start_log_stream,
+            stop_log_stream,
+            restart_backend,
+            start_backend,
+            get_backend_launch_info,
+            clear_backend_data,
+            get_effective_port,
+            check_data_dir,
+            frontend_ready,
+            list_orphan_backends,
+            kill_orphan_backends,
+            get_metrics_summary,
+            set_backend_device,
+            proxy_backend,
+            proxy_backend_image,
+            resolve_foreign_backend,
+            stop_backend,
+            reset_crash_loop,
+            rebuild_index,
+            run_backend_self_test,
+            cancel_operation,
+            get_last_self_test,
+            set_backend_log_level,
+            get_backend_logs_path,
+            open_logs_folder,
+            open_backend_docs,
+            export_diagnostics,
+            confirm_close,
+            set_backend_priority,
+            check_backend_update,
+            apply_backend_update
+        ])
+        .build(context)
+        .expect("Error running PhotoSense-AI")
+        .run(|app, event| match event {
+            // Nothing here prevents exit, so clean up as soon as it is
+            // requested: Exit is not guaranteed to follow if the process is
+            // killed in between. `cleanup_backend` only runs once.
+            tauri::RunEvent::ExitRequested { .. } | tauri::RunEvent::Exit => cleanup_backend(app),
+            _ => {}
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A second CloseRequested goes through `cleanup_backend` again and must
+    /// neither panic on a poisoned lock nor touch the state a second time.
+    #[test]
+    fn second_cleanup_is_a_no_op_even_on_a_poisoned_lock() {
+        let state = Mutex::new(BackendState::new(0));
+        #[cfg(unix)]
+        {
+            state.lock().unwrap().child = Some(Command::new("sleep").arg("30").spawn().unwrap());
+        }
+        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = state.lock().unwrap();
+            panic!("poisoning the state lock");
+        }));
+        assert!(state.is_poisoned());
+
+        assert!(cleanup_once(&state));
+        assert!(lock_state(&state).child.is_none());
+
+        #[cfg(unix)]
+        {
+            lock_state(&state).child = Some(Command::new("sleep").arg("30").spawn().unwrap());
+        }
+        assert!(!cleanup_once(&state));
+        if let Some(mut child) = lock_state(&state).child.take() {
+            assert!(child.try_wait().unwrap().is_none());
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        SHUTTING_DOWN.store(false, Ordering::SeqCst);
+    }
+
+    /// The panic hook and signal handlers may fire while the state lock is
+    /// held, so their kill path must stop the backend without it.
+    #[cfg(unix)]
+    #[test]
+    fn handler_kill_path_stops_the_backend_without_the_state_lock() {
+        let state = Mutex::new(BackendState::new(0));
+        let mut held = state.lock().unwrap();
+        let child = Command::new("sleep").arg("30").spawn().unwrap();
+        backend::track_pid(Some(child.id()));
+        held.child = Some(child);
+
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            backend::kill_tracked_backend();
+            let _ = done_tx.send(());
+        });
+        let finished = done_rx.recv_timeout(Duration::from_secs(5)).is_ok();
+
+        let child = held.child.as_mut().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut exited = false;
+        while Instant::now() < deadline {
+            if child.try_wait().unwrap().is_some() {
+                exited = true;
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        if !exited {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        backend::track_pid(None);
+        SHUTTING_DOWN.store(false, Ordering::SeqCst);
+
+        assert!(finished);
+        assert!(exited);
+    }
 }
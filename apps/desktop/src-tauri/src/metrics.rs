@@ -0,0 +1,134 @@
+//! Opt-in local lifecycle metrics. With `PHOTOSENSE_METRICS=1`, startup
+//! times, restarts and crashes are appended to `metrics.jsonl` in the data
+//! dir so slow-start reports come with real numbers. Nothing leaves the
+//! machine; the file rolls over to `metrics.jsonl.1` once it gets large.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const METRICS_ENV: &str = "PHOTOSENSE_METRICS";
+pub const METRICS_FILE: &str = "metrics.jsonl";
+const METRICS_ROTATED: &str = "metrics.jsonl.1";
+const MAX_METRICS_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum MetricEvent {
+    Startup { outcome: String, elapsed_ms: u64 },
+    Restart { attempt: u32 },
+    Crash { exit_code: Option<i32> },
+}
+
+#[derive(Serialize, Deserialize)]
+struct MetricRecord {
+    timestamp_ms: u64,
+    #[serde(flatten)]
+    event: MetricEvent,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct MetricsSummary {
+    pub enabled: bool,
+    pub startups: u32,
+    pub failed_startups: u32,
+    /// Over successful startups only.
+    pub avg_startup_ms: Option<u64>,
+    pub max_startup_ms: Option<u64>,
+    pub restarts: u32,
+    pub crashes: u32,
+    /// Crash counts keyed by exit code, or "signal" when there was none.
+    pub crash_codes: BTreeMap<String, u32>,
+    pub first_timestamp_ms: Option<u64>,
+    pub last_timestamp_ms: Option<u64>,
+}
+
+pub fn enabled() -> bool {
+    std::env::var(METRICS_ENV).as_deref() == Ok("1")
+}
+
+pub struct MetricsRecorder {
+    path: PathBuf,
+}
+
+impl MetricsRecorder {
+    /// Recorder for `data_dir`, or `None` when metrics are not enabled.
+    pub fn new(data_dir: &Path) -> Option<Self> {
+        enabled().then(|| MetricsRecorder {
+            path: data_dir.join(METRICS_FILE),
+        })
+    }
+
+    pub fn record(&self, event: MetricEvent) -> Result<(), String> {
+        if fs::metadata(&self.path).is_ok_and(|m| m.len() >= MAX_METRICS_BYTES) {
+            let _ = fs::rename(&self.path, self.path.with_file_name(METRICS_ROTATED));
+        }
+        let record = MetricRecord {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            event,
+        };
+        let mut line = serde_json::to_string(&record).map_err(|e| e.to_string())?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| format!("Failed to write metrics: {e}"))
+    }
+}
+
+/// Aggregates both the current and rolled-over metrics files. Lines that
+/// don't parse are skipped.
+pub fn summarize(data_dir: &Path) -> MetricsSummary {
+    let mut summary = MetricsSummary {
+        enabled: enabled(),
+        ..Default::default()
+    };
+    let mut startup_total = 0;
+    let mut succeeded = 0;
+    for name in [METRICS_ROTATED, METRICS_FILE] {
+        let text = match fs::read_to_string(data_dir.join(name)) {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+        for record in text
+            .lines()
+            .filter_map(|line| serde_json::from_str::<MetricRecord>(line).ok())
+        {
+            summary
+                .first_timestamp_ms
+                .get_or_insert(record.timestamp_ms);
+            summary.last_timestamp_ms = Some(record.timestamp_ms);
+            match record.event {
+                MetricEvent::Startup {
+                    outcome,
+                    elapsed_ms,
+                } => {
+                    summary.startups += 1;
+                    if outcome != "ready" {
+                        summary.failed_startups += 1;
+                        continue;
+                    }
+                    succeeded += 1;
+                    startup_total += elapsed_ms;
+                    summary.max_startup_ms = summary.max_startup_ms.max(Some(elapsed_ms));
+                }
+                MetricEvent::Restart { .. } => summary.restarts += 1,
+                MetricEvent::Crash { exit_code } => {
+                    summary.crashes += 1;
+                    let key = exit_code.map_or("signal".to_string(), |c| c.to_string());
+                    *summary.crash_codes.entry(key).or_default() += 1;
+                }
+            }
+        }
+    }
+    summary.avg_startup_ms = startup_total.checked_div(succeeded);
+    summary
+}
@@ -0,0 +1,168 @@
+//! Clearing the data dir for "reset to defaults". Everything under the
+//! directory is removed except the names the caller asks to keep; nothing
+//! outside it is ever touched, symlinks included.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Deletes the contents of `data_dir` except the top-level entries named in
+/// `keep` and returns the number of bytes freed. A missing directory frees
+/// nothing.
+///
+/// `PHOTOSENSE_DATA_DIR` can point anywhere, so the directory is only cleared
+/// if it is `app_data_dir` or holds one of `markers`, files the app writes
+/// into its data dir. A filesystem root or the home directory is refused
+/// regardless.
+pub fn clear_data_dir(
+    data_dir: &Path,
+    app_data_dir: Option<&Path>,
+    markers: &[&str],
+    keep: &[&str],
+) -> Result<u64, String> {
+    let root = match fs::canonicalize(data_dir) {
+        Ok(root) => root,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(format!("Failed to resolve {}: {e}", data_dir.display())),
+    };
+    let home = tauri::api::path::home_dir().and_then(|home| fs::canonicalize(home).ok());
+    if root.parent().is_none() || home.as_deref() == Some(root.as_path()) {
+        return Err(format!("Refusing to clear {}", root.display()));
+    }
+    let is_app_dir = app_data_dir
+        .and_then(|dir| fs::canonicalize(dir).ok())
+        .is_some_and(|dir| dir == root);
+    if !is_app_dir && !markers.iter().any(|name| root.join(name).exists()) {
+        return Err(format!(
+            "Refusing to clear {}: it doesn't look like a PhotoSense data dir",
+            root.display()
+        ));
+    }
+
+    let entries =
+        fs::read_dir(&root).map_err(|e| format!("Failed to read {}: {e}", root.display()))?;
+    let mut freed = 0;
+    for entry in entries.filter_map(Result::ok) {
+        if keep.iter().any(|name| entry.file_name() == *name) {
+            continue;
+        }
+        let path = root.join(entry.file_name());
+        // Read the entry itself rather than its target, so a symlink is
+        // removed without following it out of the data dir.
+        let meta = match fs::symlink_metadata(&path) {
+            Ok(meta) => meta,
+            Err(_) => continue,
+        };
+        let size = if meta.is_dir() {
+            dir_size(&path)
+        } else {
+            meta.len()
+        };
+        let removed = if meta.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        removed.map_err(|e| format!("Failed to delete {}: {e}", path.display()))?;
+        freed += size;
+    }
+    Ok(freed)
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let meta = fs::symlink_metadata(entry.path()).ok()?;
+            Some(if meta.is_dir() {
+                dir_size(&entry.path())
+            } else {
+                meta.len()
+            })
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const MARKER: &str = "instance.lock";
+
+    fn scratch(name: &str) -> PathBuf {
+        let name = format!("photosense-reset-{}-{name}", std::process::id());
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn missing_dir_frees_nothing() {
+        let dir = scratch("missing").join("absent");
+        assert_eq!(clear_data_dir(&dir, None, &[MARKER], &[]), Ok(0));
+    }
+
+    #[test]
+    fn refuses_a_dir_without_a_marker() {
+        let dir = scratch("unmarked");
+        fs::write(dir.join("photo.jpg"), b"jpeg").unwrap();
+        assert!(clear_data_dir(&dir, None, &[MARKER], &[]).is_err());
+        assert!(dir.join("photo.jpg").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    // Neither has a marker, so a regression in the root or home check still
+    // stops at the marker check; the exact message shows which one refused.
+    #[test]
+    fn refuses_the_filesystem_root_and_home() {
+        let root = fs::canonicalize("/").unwrap();
+        let refused = format!("Refusing to clear {}", root.display());
+        assert_eq!(clear_data_dir(&root, None, &[], &[]), Err(refused));
+
+        if let Some(home) = tauri::api::path::home_dir().and_then(|h| fs::canonicalize(h).ok()) {
+            let refused = format!("Refusing to clear {}", home.display());
+            assert_eq!(clear_data_dir(&home, None, &[], &[]), Err(refused));
+        }
+    }
+
+    #[test]
+    fn clears_everything_but_keep() {
+        let dir = scratch("keep");
+        fs::write(dir.join(MARKER), b"1234").unwrap();
+        fs::write(dir.join("config.json"), b"{}").unwrap();
+        fs::create_dir_all(dir.join("cache/thumbs")).unwrap();
+        fs::write(dir.join("cache/thumbs/a.jpg"), b"123456").unwrap();
+
+        let freed = clear_data_dir(&dir, None, &[MARKER], &["config.json"]).unwrap();
+        assert_eq!(freed, 10);
+        let left: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(left, ["config.json"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_out_of_the_dir_are_unlinked_not_followed() {
+        let dir = scratch("symlink");
+        let outside = scratch("symlink-target");
+        fs::write(outside.join("keepsake.jpg"), b"jpeg").unwrap();
+        fs::write(dir.join(MARKER), b"").unwrap();
+        std::os::unix::fs::symlink(&outside, dir.join("photos")).unwrap();
+        std::os::unix::fs::symlink(outside.join("keepsake.jpg"), dir.join("cover.jpg")).unwrap();
+
+        clear_data_dir(&dir, None, &[MARKER], &[]).unwrap();
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        assert_eq!(fs::read(outside.join("keepsake.jpg")).unwrap(), b"jpeg");
+        let _ = fs::remove_dir_all(&dir);
+        let _ = fs::remove_dir_all(&outside);
+    }
+}
@@ -0,0 +1,132 @@
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+pub const LOCK_FILE_NAME: &str = "instance.lock";
+const FOCUS_REQUEST: &str = "focus";
+const FOCUS_ACK: &str = "photosense";
+
+pub enum Instance {
+    /// We own the lock; `serve` must be called right away, before anything
+    /// slow like the data dir migration, or a second launch sees no answer.
+    /// `None` when the guard could not be set up and we run unguarded.
+    Primary(Option<TcpListener>),
+    /// Another instance answered and has been asked to focus its window.
+    Secondary,
+}
+
+/// Takes the single-instance lock in `data_dir`. The lock file holds the port
+/// of a loopback listener; it is never removed explicitly, since a lock whose
+/// listener no longer answers is treated as stale.
+pub fn acquire(data_dir: &Path) -> Instance {
+    let lock_file = data_dir.join(LOCK_FILE_NAME);
+    let _ = fs::create_dir_all(data_dir);
+
+    let listener = match TcpListener::bind("127.0.0.1:0") {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("[Warning] Single-instance guard unavailable: {}", e);
+            return Instance::Primary(None);
+        }
+    };
+    let port = listener.local_addr().map(|a| a.port()).unwrap_or(0);
+
+    for _ in 0..2 {
+        match OpenOptions::new().write(true).create_new(true).open(&lock_file) {
+            Ok(mut file) => {
+                let _ = writeln!(file, "{}", port);
+                return Instance::Primary(Some(listener));
+            }
+            Err(_) => {
+                if notify_primary(&lock_file) {
+                    return Instance::Secondary;
+                }
+                // Nobody answered: the lock was left behind by a previous run.
+                let _ = fs::remove_file(&lock_file);
+            }
+        }
+    }
+
+    eprintln!("[Warning] Could not take the single-instance lock, continuing anyway");
+    Instance::Primary(Some(listener))
+}
+
+fn notify_primary(lock_file: &Path) -> bool {
+    let port: u16 = match fs::read_to_string(lock_file)
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+    {
+        Some(port) => port,
+        None => return false,
+    };
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let mut stream = match TcpStream::connect_timeout(&addr, Duration::from_millis(500)) {
+        Ok(stream) => stream,
+        Err(_) => return false,
+    };
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(1)));
+    if writeln!(stream, "{}", FOCUS_REQUEST).is_err() {
+        return false;
+    }
+    let mut reply = String::new();
+    let _ = BufReader::new(stream).read_line(&mut reply);
+    reply.trim() == FOCUS_ACK
+}
+
+#[derive(Default)]
+struct FocusState {
+    app: Option<AppHandle>,
+    /// A launch asked for focus before the app was attached.
+    pending: bool,
+}
+
+/// Where focus requests go once the app is running; see `serve`.
+pub struct FocusTarget(Arc<Mutex<FocusState>>);
+
+impl FocusTarget {
+    /// Starts focusing the main window on request, including any request
+    /// that arrived while the app was still starting.
+    pub fn attach(self, app: AppHandle) {
+        let mut state = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if std::mem::take(&mut state.pending) {
+            focus_main_window(&app);
+        }
+        state.app = Some(app);
+    }
+}
+
+fn focus_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_window("main") {
+        crate::bring_to_front(&window);
+    }
+}
+
+/// Answers other launches from now on. Requests that arrive before
+/// `FocusTarget::attach` are acknowledged and held until then.
+pub fn serve(listener: TcpListener) -> FocusTarget {
+    let state = Arc::new(Mutex::new(FocusState::default()));
+    let shared = Arc::clone(&state);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(1)));
+            let mut line = String::new();
+            if BufReader::new(&stream).read_line(&mut line).is_err()
+                || line.trim() != FOCUS_REQUEST
+            {
+                continue;
+            }
+            let _ = writeln!(&stream, "{}", FOCUS_ACK);
+            let mut state = shared.lock().unwrap_or_else(PoisonError::into_inner);
+            match state.app.as_ref() {
+                Some(app) => focus_main_window(app),
+                None => state.pending = true,
+            }
+        }
+    });
+    FocusTarget(state)
+}
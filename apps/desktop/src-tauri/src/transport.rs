@@ -0,0 +1,169 @@
+//! How the shell reaches the backend: TCP by default, or with
+//! `PHOTOSENSE_TRANSPORT=uds` a Unix domain socket in the data dir, which
+//! avoids port conflicts and keeps other users off the API entirely. The
+//! webview can't talk to a socket, so the frontend goes through the
+//! `proxy_backend` command in that mode. Windows always uses TCP.
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const TRANSPORT_ENV: &str = "PHOTOSENSE_TRANSPORT";
+pub const UDS_PATH_ENV: &str = "PHOTOSENSE_UDS_PATH";
+#[cfg(unix)]
+const SOCKET_DIR: &str = "run";
+#[cfg(unix)]
+const SOCKET_FILE: &str = "backend.sock";
+/// `sun_path` is 104 bytes on macOS and 108 on Linux, including the NUL.
+#[cfg(unix)]
+const MAX_SOCKET_PATH_LEN: usize = 100;
+
+static SOCKET_PATH: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// Resolves the transport from the environment. Must run once at startup,
+/// before anything connects to the backend; until then TCP is used.
+pub fn init(data_dir: &Path) {
+    let requested = std::env::var(TRANSPORT_ENV).unwrap_or_default();
+    let path = match requested.trim().to_lowercase().as_str() {
+        "" | "tcp" => None,
+        "uds" => socket_path_for(data_dir),
+        other => {
+            eprintln!(
+                "[Error] Invalid {} value '{}', using tcp",
+                TRANSPORT_ENV, other
+            );
+            None
+        }
+    };
+    let _ = SOCKET_PATH.set(path);
+}
+
+#[cfg(unix)]
+fn socket_path_for(data_dir: &Path) -> Option<PathBuf> {
+    let path = data_dir.join(SOCKET_DIR).join(SOCKET_FILE);
+    if path.as_os_str().len() > MAX_SOCKET_PATH_LEN {
+        eprintln!(
+            "[Warning] Socket path {} is too long for a Unix socket, using tcp",
+            path.display()
+        );
+        return None;
+    }
+    Some(path)
+}
+
+#[cfg(not(unix))]
+fn socket_path_for(_data_dir: &Path) -> Option<PathBuf> {
+    eprintln!("[Warning] Unix socket transport is not supported here, using tcp");
+    None
+}
+
+/// The backend's socket when the socket transport is active.
+pub fn socket_path() -> Option<&'static Path> {
+    SOCKET_PATH.get().and_then(|path| path.as_deref())
+}
+
+pub fn name() -> &'static str {
+    if socket_path().is_some() {
+        "uds"
+    } else {
+        "tcp"
+    }
+}
+
+/// Readies the socket location before a backend is spawned: creates its
+/// directory owner-only, so other local users can't reach the socket, and
+/// removes a socket file left behind by a backend that is no longer
+/// listening, which would otherwise stop the next one from binding.
+pub fn prepare_socket() -> Result<(), String> {
+    #[cfg(unix)]
+    if let Some(path) = socket_path() {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+        if let Some(dir) = path.parent() {
+            std::fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(dir)
+                .and_then(|_| std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700)))
+                .map_err(|e| format!("Failed to create socket directory {}: {e}", dir.display()))?;
+        }
+        if path.exists() && std::os::unix::net::UnixStream::connect(path).is_err() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+    Ok(())
+}
+
+pub enum BackendStream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixStream),
+}
+
+impl BackendStream {
+    /// Connects to the socket if that transport is active. `None` means the
+    /// caller should connect over TCP.
+    pub fn connect_socket() -> Option<io::Result<Self>> {
+        #[cfg(unix)]
+        if let Some(path) = socket_path() {
+            return Some(std::os::unix::net::UnixStream::connect(path).map(BackendStream::Unix));
+        }
+        None
+    }
+
+    /// Value for the HTTP `Host` header.
+    pub fn host(&self) -> String {
+        match self {
+            BackendStream::Tcp(stream) => stream
+                .peer_addr()
+                .map(|addr| addr.to_string())
+                .unwrap_or_else(|_| "localhost".to_string()),
+            #[cfg(unix)]
+            BackendStream::Unix(_) => "localhost".to_string(),
+        }
+    }
+
+    pub fn set_timeouts(&self, timeout: Duration) {
+        match self {
+            BackendStream::Tcp(stream) => {
+                let _ = stream.set_read_timeout(Some(timeout));
+                let _ = stream.set_write_timeout(Some(timeout));
+            }
+            #[cfg(unix)]
+            BackendStream::Unix(stream) => {
+                let _ = stream.set_read_timeout(Some(timeout));
+                let _ = stream.set_write_timeout(Some(timeout));
+            }
+        }
+    }
+}
+
+impl Read for BackendStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            BackendStream::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            BackendStream::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for BackendStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            BackendStream::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            BackendStream::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            BackendStream::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            BackendStream::Unix(stream) => stream.flush(),
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use crate::backend::{cached_health, SHUTTING_DOWN};
+use crate::{
+    cleanup_backend, lock_state, log_line, log_path, restart_backend_blocking, reveal_path,
+    BackendState,
+};
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{
+    AppHandle, CustomMenuItem, Manager, SystemTray, SystemTrayEvent, SystemTrayMenu,
+    SystemTrayMenuItem,
+};
+
+const STATUS_REFRESH_SECS: u64 = 5;
+
+pub fn system_tray() -> SystemTray {
+    let menu = SystemTrayMenu::new()
+        .add_item(CustomMenuItem::new("status", "Backend: Starting...").disabled())
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new("restart", "Restart Backend"))
+        .add_item(CustomMenuItem::new("open_logs", "Open Logs Folder"))
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new("quit", "Quit"));
+    SystemTray::new().with_menu(menu)
+}
+
+pub fn handle_tray_event(app: &AppHandle, event: SystemTrayEvent) {
+    if let SystemTrayEvent::MenuItemClick { id, .. } = event {
+        match id.as_str() {
+            "restart" => {
+                let app = app.clone();
+                thread::spawn(move || {
+                    if let Err(e) = restart_backend_blocking(&app) {
+                        eprintln!("[Error] Backend restart failed: {}", e);
+                        log_line(&app, &format!("[Error] Backend restart failed: {e}"));
+                    }
+                });
+            }
+            "open_logs" => {
+                if let Err(e) = reveal_path(&log_path(app)) {
+                    eprintln!("[Error] {}", e);
+                }
+            }
+            "quit" => {
+                cleanup_backend(app);
+                app.exit(0);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn spawn_status_refresher(app: AppHandle) {
+    thread::spawn(move || {
+        while !SHUTTING_DOWN.load(Ordering::SeqCst) {
+            let port = lock_state(&app.state::<Mutex<BackendState>>()).port;
+            let label = if cached_health(port, false).healthy {
+                "Backend: Running"
+            } else {
+                "Backend: Stopped"
+            };
+            let _ = app.tray_handle().get_item("status").set_title(label);
+            thread::sleep(Duration::from_secs(STATUS_REFRESH_SECS));
+        }
+    });
+}
@@ -0,0 +1,235 @@
+//! Backend-only updates. A JSON manifest names the latest backend build for
+//! each platform; the archive is downloaded, checksum-verified and unpacked
+//! into a staging directory under the data dir, and promoted to the active
+//! backend the next time it starts. Downloads go through the system `curl`,
+//! which ships with macOS, Windows 10+ and practically every Linux desktop.
+//!
+//! Manifest format:
+//! `{"version": "1.2.0", "platforms": {"macos-aarch64": {"url": "https://...", "sha256": "..."}}}`
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub const UPDATE_URL_ENV: &str = "PHOTOSENSE_BACKEND_UPDATE_URL";
+const UPDATE_DIR: &str = "backend-update";
+const STAGED_DIR: &str = "staged";
+const ACTIVE_DIR: &str = "active";
+const VERSION_FILE: &str = "VERSION";
+const MANIFEST_TIMEOUT_SECS: u64 = 30;
+const DOWNLOAD_TIMEOUT_SECS: u64 = 1800;
+
+#[derive(Deserialize)]
+struct Manifest {
+    version: String,
+    platforms: HashMap<String, Artifact>,
+}
+
+#[derive(Clone, Deserialize)]
+struct Artifact {
+    url: String,
+    sha256: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct UpdateInfo {
+    pub current_version: String,
+    pub latest_version: String,
+    pub available: bool,
+}
+
+fn platform_key() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+fn manifest_url() -> Result<String, String> {
+    match std::env::var(UPDATE_URL_ENV) {
+        Ok(url) if url.trim().starts_with("https://") => Ok(url.trim().to_string()),
+        Ok(url) => Err(format!(
+            "{} must be an https:// URL, got '{}'",
+            UPDATE_URL_ENV, url
+        )),
+        Err(_) => Err(format!(
+            "No backend update source configured ({})",
+            UPDATE_URL_ENV
+        )),
+    }
+}
+
+/// Runs curl, writing to `output` or returning the body when it is `None`.
+fn curl(url: &str, output: Option<&Path>, timeout_secs: u64) -> Result<Vec<u8>, String> {
+    let mut command = Command::new("curl");
+    command.args([
+        "-fsSL",
+        "--proto",
+        "=https",
+        "--max-time",
+        &timeout_secs.to_string(),
+    ]);
+    if let Some(path) = output {
+        command.arg("-o").arg(path);
+    }
+    command.arg(url);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(crate::backend::CREATE_NO_WINDOW);
+    }
+    let out = command
+        .output()
+        .map_err(|e| format!("Failed to run curl: {e}"))?;
+    if !out.status.success() {
+        return Err(format!(
+            "Download of {} failed: {}",
+            url,
+            String::from_utf8_lossy(&out.stderr).trim()
+        ));
+    }
+    Ok(out.stdout)
+}
+
+fn fetch_manifest() -> Result<Manifest, String> {
+    let body = curl(&manifest_url()?, None, MANIFEST_TIMEOUT_SECS)?;
+    serde_json::from_slice(&body).map_err(|e| format!("Invalid update manifest: {e}"))
+}
+
+/// Semantic comparison when both sides parse, otherwise any difference
+/// counts as newer.
+fn is_newer(latest: &str, current: &str) -> bool {
+    let parse = |v: &str| semver::Version::parse(v.trim().trim_start_matches('v'));
+    match (parse(latest), parse(current)) {
+        (Ok(latest), Ok(current)) => latest > current,
+        _ => latest.trim() != current.trim(),
+    }
+}
+
+pub fn check(current_version: &str) -> Result<UpdateInfo, String> {
+    let manifest = fetch_manifest()?;
+    let available = manifest.platforms.contains_key(&platform_key())
+        && is_newer(&manifest.version, current_version);
+    Ok(UpdateInfo {
+        current_version: current_version.to_string(),
+        latest_version: manifest.version,
+        available,
+    })
+}
+
+fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = fs::File::open(path).map_err(|e| format!("Failed to read download: {e}"))?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buf)
+            .map_err(|e| format!("Failed to read download: {e}"))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Downloads and verifies the latest backend for this platform and unpacks
+/// it into the staging directory, replacing anything staged earlier.
+/// Returns the staged version.
+pub fn stage(data_dir: &Path) -> Result<String, String> {
+    let manifest = fetch_manifest()?;
+    let artifact = manifest
+        .platforms
+        .get(&platform_key())
+        .cloned()
+        .ok_or_else(|| format!("No backend update published for {}", platform_key()))?;
+    if !artifact.url.starts_with("https://") {
+        return Err(format!("Refusing non-https update URL {}", artifact.url));
+    }
+
+    let update_dir = data_dir.join(UPDATE_DIR);
+    fs::create_dir_all(&update_dir)
+        .map_err(|e| format!("Failed to create update directory: {e}"))?;
+    let archive_path = update_dir.join("download.tar.gz");
+    curl(&artifact.url, Some(&archive_path), DOWNLOAD_TIMEOUT_SECS)?;
+
+    let actual = sha256_file(&archive_path)?;
+    if !actual.eq_ignore_ascii_case(artifact.sha256.trim()) {
+        let _ = fs::remove_file(&archive_path);
+        return Err(format!(
+            "Checksum mismatch for backend update: expected {}, got {}",
+            artifact.sha256, actual
+        ));
+    }
+
+    // Unpack beside the staging dir and rename, so a failed extraction never
+    // leaves a half-written build staged.
+    let unpack_dir = update_dir.join(format!("{}.tmp", STAGED_DIR));
+    let _ = fs::remove_dir_all(&unpack_dir);
+    let archive =
+        fs::File::open(&archive_path).map_err(|e| format!("Failed to open update: {e}"))?;
+    tar::Archive::new(flate2::read::GzDecoder::new(archive))
+        .unpack(&unpack_dir)
+        .map_err(|e| format!("Failed to unpack backend update: {e}"))?;
+    fs::write(unpack_dir.join(VERSION_FILE), &manifest.version)
+        .map_err(|e| format!("Failed to write update version: {e}"))?;
+    let _ = fs::remove_file(&archive_path);
+
+    let staged = update_dir.join(STAGED_DIR);
+    let _ = fs::remove_dir_all(&staged);
+    fs::rename(&unpack_dir, &staged).map_err(|e| format!("Failed to stage backend update: {e}"))?;
+    Ok(manifest.version)
+}
+
+/// Moves a staged update into place. Must only run while no backend started
+/// from the active directory is running. Returns the installed version.
+pub fn promote_staged(data_dir: &Path) -> Result<Option<String>, String> {
+    let update_dir = data_dir.join(UPDATE_DIR);
+    let staged = update_dir.join(STAGED_DIR);
+    if !staged.is_dir() {
+        return Ok(None);
+    }
+    let active = update_dir.join(ACTIVE_DIR);
+    if active.exists() {
+        fs::remove_dir_all(&active)
+            .map_err(|e| format!("Failed to remove previous backend update: {e}"))?;
+    }
+    fs::rename(&staged, &active).map_err(|e| format!("Failed to install backend update: {e}"))?;
+    Ok(Some(
+        fs::read_to_string(active.join(VERSION_FILE))
+            .map(|v| v.trim().to_string())
+            .unwrap_or_default(),
+    ))
+}
+
+/// The updated backend binary, if an update has been installed.
+pub fn active_binary(data_dir: &Path, names: &[&str]) -> Option<PathBuf> {
+    let active = data_dir.join(UPDATE_DIR).join(ACTIVE_DIR);
+    names
+        .iter()
+        .map(|name| active.join(name))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_compare_semantically_with_or_without_v() {
+        assert!(is_newer("v1.2.0", "1.1.9"));
+        assert!(is_newer("1.10.0", "v1.9.3"));
+        assert!(!is_newer(" v1.2.0 ", "1.2.0"));
+        assert!(!is_newer("1.1.0", "1.2.0"));
+        assert!(is_newer("1.2.0", "1.2.0-beta.1"));
+    }
+
+    #[test]
+    fn non_semver_versions_are_newer_when_they_differ() {
+        assert!(is_newer("2024.06", "2024.05"));
+        assert!(is_newer("2024.05", "2024.06"));
+        assert!(is_newer("1.2", "1.2.0"));
+        assert!(!is_newer("nightly", " nightly "));
+    }
+}
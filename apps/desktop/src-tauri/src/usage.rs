@@ -0,0 +1,292 @@
+//! CPU and memory usage of the backend process and free space on its data
+//! volume, read straight from the OS so we don't need a full
+//! system-information crate for one PID.
+
+use serde::Serialize;
+use std::io;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const CPU_SAMPLE_WINDOW_MS: u64 = 250;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceUsage {
+    pub cpu_percent: f64,
+    pub memory_mb: f64,
+    pub threads: u32,
+}
+
+/// CPU is measured over a short sampling window, so this blocks for about
+/// `CPU_SAMPLE_WINDOW_MS`. The percentage is per core, like `top`, and can
+/// exceed 100 on multi-core machines.
+#[cfg(any(target_os = "linux", target_os = "macos", windows))]
+pub fn sample(pid: u32) -> Result<ResourceUsage, String> {
+    let started = Instant::now();
+    let cpu_before = platform::cpu_time(pid)?;
+    thread::sleep(Duration::from_millis(CPU_SAMPLE_WINDOW_MS));
+    let cpu_after = platform::cpu_time(pid)?;
+    let wall = started.elapsed().as_secs_f64();
+    let cpu_percent = if wall > 0.0 {
+        cpu_after.saturating_sub(cpu_before).as_secs_f64() / wall * 100.0
+    } else {
+        0.0
+    };
+
+    Ok(ResourceUsage {
+        cpu_percent,
+        memory_mb: platform::resident_bytes(pid)? as f64 / (1024.0 * 1024.0),
+        threads: platform::thread_count(pid)?,
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn sample(_pid: u32) -> Result<ResourceUsage, String> {
+    Err("Resource usage is not supported on this platform".to_string())
+}
+
+/// Bytes available to unprivileged users on the volume holding `path`.
+#[cfg(unix)]
+pub fn free_disk_space(path: &Path) -> io::Result<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    // SAFETY: statvfs is plain data; zeroed is valid, and `c_path` is a
+    // NUL-terminated string that outlives the call.
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(windows)]
+pub fn free_disk_space(path: &Path) -> io::Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0u64;
+    // SAFETY: `wide` is NUL-terminated and the out-pointer references a
+    // live u64; the other outputs are optional.
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(available)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn free_disk_space(_path: &Path) -> io::Result<u64> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "not supported on this platform"))
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs;
+    use std::time::Duration;
+
+    /// Fields of `/proc/<pid>/stat` after the parenthesised command name,
+    /// which may itself contain spaces.
+    fn stat_fields(pid: u32) -> Result<Vec<String>, String> {
+        let stat = fs::read_to_string(format!("/proc/{}/stat", pid))
+            .map_err(|e| format!("Failed to read process stats: {e}"))?;
+        let rest = stat
+            .rsplit_once(')')
+            .map(|(_, rest)| rest)
+            .ok_or("Unexpected /proc stat format")?;
+        Ok(rest.split_whitespace().map(str::to_string).collect())
+    }
+
+    fn field<T: std::str::FromStr>(fields: &[String], index: usize) -> Result<T, String> {
+        fields
+            .get(index)
+            .and_then(|f| f.parse().ok())
+            .ok_or_else(|| "Unexpected /proc stat format".to_string())
+    }
+
+    pub fn cpu_time(pid: u32) -> Result<Duration, String> {
+        // utime and stime are fields 14 and 15 of the full line; the slice
+        // starts at field 3.
+        let fields = stat_fields(pid)?;
+        let ticks: u64 = field::<u64>(&fields, 11)? + field::<u64>(&fields, 12)?;
+        // SAFETY: sysconf only reads a system constant.
+        let per_sec = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        if per_sec <= 0 {
+            return Err("Failed to read clock tick rate".to_string());
+        }
+        Ok(Duration::from_secs_f64(ticks as f64 / per_sec as f64))
+    }
+
+    pub fn thread_count(pid: u32) -> Result<u32, String> {
+        field(&stat_fields(pid)?, 17)
+    }
+
+    pub fn resident_bytes(pid: u32) -> Result<u64, String> {
+        let status = fs::read_to_string(format!("/proc/{}/status", pid))
+            .map_err(|e| format!("Failed to read process status: {e}"))?;
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))
+            .and_then(|value| value.split_whitespace().next()?.parse::<u64>().ok())
+            .map(|kb| kb * 1024)
+            .ok_or_else(|| "VmRSS missing from process status".to_string())
+    }
+}
+
+/// macOS has no /proc, and `ps -o %cpu` there is a decaying average rather
+/// than a delta, so the cumulative CPU time is sampled twice like elsewhere.
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::process::Command;
+    use std::time::Duration;
+
+    fn ps(pid: u32, args: &[&str]) -> Result<String, String> {
+        let output = Command::new("ps")
+            .args(args)
+            .args(["-p", &pid.to_string()])
+            .output()
+            .map_err(|e| format!("Failed to run ps: {e}"))?;
+        if !output.status.success() {
+            return Err(format!("Process {} not found", pid));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Parses `ps` CPU time: `[[dd-]hh:]mm:ss[.ss]`.
+    fn parse_cpu_time(value: &str) -> Option<Duration> {
+        let (days, clock) = match value.trim().split_once('-') {
+            Some((days, clock)) => (days.parse::<u64>().ok()?, clock),
+            None => (0, value.trim()),
+        };
+        let mut parts = clock.rsplit(':');
+        let secs = parts.next()?.parse::<f64>().ok()?;
+        let mut total = secs + days as f64 * 86_400.0;
+        for (unit, part) in [60.0, 3600.0].into_iter().zip(parts.by_ref()) {
+            total += part.parse::<u64>().ok()? as f64 * unit;
+        }
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Duration::from_secs_f64(total))
+    }
+
+    pub fn cpu_time(pid: u32) -> Result<Duration, String> {
+        parse_cpu_time(&ps(pid, &["-o", "time="])?)
+            .ok_or_else(|| "Unexpected ps output".to_string())
+    }
+
+    pub fn resident_bytes(pid: u32) -> Result<u64, String> {
+        ps(pid, &["-o", "rss="])?
+            .trim()
+            .parse::<u64>()
+            .map(|kb| kb * 1024)
+            .map_err(|_| "Unexpected ps output".to_string())
+    }
+
+    pub fn thread_count(pid: u32) -> Result<u32, String> {
+        // `ps -M` prints a header followed by one line per thread.
+        let lines = ps(pid, &["-M"])?.lines().count();
+        Ok(lines.saturating_sub(1) as u32)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::time::Duration;
+    use windows_sys::Win32::Foundation::{CloseHandle, FILETIME, HANDLE, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+    };
+    use windows_sys::Win32::System::ProcessStatus::{
+        K32GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS,
+    };
+    use windows_sys::Win32::System::Threading::{
+        GetProcessTimes, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_VM_READ,
+    };
+
+    struct Handle(HANDLE);
+
+    impl Drop for Handle {
+        fn drop(&mut self) {
+            // SAFETY: the handle was returned by a successful Win32 call and
+            // is closed exactly once.
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+
+    fn open(pid: u32) -> Result<Handle, String> {
+        // SAFETY: plain Win32 call with integer arguments.
+        let handle =
+            unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION | PROCESS_VM_READ, 0, pid) };
+        if handle == 0 {
+            return Err(format!("Failed to open process {}", pid));
+        }
+        Ok(Handle(handle))
+    }
+
+    fn filetime(ft: &FILETIME) -> u64 {
+        ((ft.dwHighDateTime as u64) << 32) | ft.dwLowDateTime as u64
+    }
+
+    pub fn cpu_time(pid: u32) -> Result<Duration, String> {
+        let process = open(pid)?;
+        let zero = FILETIME { dwLowDateTime: 0, dwHighDateTime: 0 };
+        let (mut created, mut exited, mut kernel, mut user) = (zero, zero, zero, zero);
+        // SAFETY: all out-pointers reference live stack values.
+        let ok = unsafe {
+            GetProcessTimes(process.0, &mut created, &mut exited, &mut kernel, &mut user)
+        };
+        if ok == 0 {
+            return Err("Failed to read process times".to_string());
+        }
+        // FILETIME counts 100ns intervals.
+        Ok(Duration::from_nanos((filetime(&kernel) + filetime(&user)) * 100))
+    }
+
+    pub fn resident_bytes(pid: u32) -> Result<u64, String> {
+        let process = open(pid)?;
+        let size = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+        // SAFETY: PROCESS_MEMORY_COUNTERS is plain data; zeroed is valid.
+        let mut counters: PROCESS_MEMORY_COUNTERS = unsafe { std::mem::zeroed() };
+        counters.cb = size;
+        // SAFETY: `counters` is a live, correctly sized out-buffer.
+        if unsafe { K32GetProcessMemoryInfo(process.0, &mut counters, size) } == 0 {
+            return Err("Failed to read process memory".to_string());
+        }
+        Ok(counters.WorkingSetSize as u64)
+    }
+
+    pub fn thread_count(pid: u32) -> Result<u32, String> {
+        // SAFETY: plain Win32 call with integer arguments.
+        let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0) };
+        if snapshot == INVALID_HANDLE_VALUE {
+            return Err("Failed to snapshot threads".to_string());
+        }
+        let snapshot = Handle(snapshot);
+        // SAFETY: THREADENTRY32 is plain data; zeroed is valid.
+        let mut entry: THREADENTRY32 = unsafe { std::mem::zeroed() };
+        entry.dwSize = std::mem::size_of::<THREADENTRY32>() as u32;
+        let mut count = 0;
+        // SAFETY: `entry` is a live out-buffer with dwSize set.
+        let mut more = unsafe { Thread32First(snapshot.0, &mut entry) } != 0;
+        while more {
+            if entry.th32OwnerProcessID == pid {
+                count += 1;
+            }
+            // SAFETY: as above.
+            more = unsafe { Thread32Next(snapshot.0, &mut entry) } != 0;
+        }
+        Ok(count)
+    }
+}
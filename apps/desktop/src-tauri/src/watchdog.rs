@@ -0,0 +1,114 @@
+//! Watches a running backend over HTTP: consecutive failed health checks
+//! mean it is offline, and a rising average probe latency that it is stalled.
+//! Either can optionally restart it (`PHOTOSENSE_WATCHDOG_RESTART=1`).
+
+use crate::backend::{cached_health, health_timeout, SHUTTING_DOWN};
+use crate::{lock_state, log_line, restart_backend_blocking, set_status_title, BackendState};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+const WATCHDOG_INTERVAL_SECS: u64 = 10;
+const WATCHDOG_MAX_FAILURES: u32 = 3;
+const WATCHDOG_RESTART_ENV: &str = "PHOTOSENSE_WATCHDOG_RESTART";
+pub const LATENCY_SAMPLES: usize = 6;
+
+/// Probes time out after the health timeout and count as failures, so the
+/// stall threshold sits below it: 1.5s at the default 2s, and scaling with
+/// `PHOTOSENSE_HEALTH_TIMEOUT_SECS` on machines where the backend is slow.
+fn stall_threshold() -> Duration {
+    health_timeout() * 3 / 4
+}
+
+/// Background health monitor for a running backend. Catches the case where
+/// the process is alive but no longer answering HTTP.
+pub struct Watchdog {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl Watchdog {
+    pub fn spawn(app: AppHandle) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+        let handle = thread::spawn(move || run(&app, &stop_flag));
+        Watchdog { stop, handle }
+    }
+
+    pub fn stop(self) {
+        self.stop.store(true, Ordering::SeqCst);
+        self.handle.thread().unpark();
+        let _ = self.handle.join();
+    }
+}
+
+fn run(app: &AppHandle, stop: &AtomicBool) {
+    let state = app.state::<Mutex<BackendState>>();
+    let mut failures = 0;
+    loop {
+        thread::park_timeout(Duration::from_secs(WATCHDOG_INTERVAL_SECS));
+        if stop.load(Ordering::SeqCst) || SHUTTING_DOWN.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let port = lock_state(&state).port;
+        // Always probe for real: a cached result has no latency to record.
+        let probe_start = Instant::now();
+        if cached_health(port, true).healthy {
+            failures = 0;
+            let average = {
+                let mut state_guard = lock_state(&state);
+                let samples = &mut state_guard.health_latencies;
+                if samples.len() == LATENCY_SAMPLES {
+                    samples.pop_front();
+                }
+                samples.push_back(probe_start.elapsed());
+                if samples.len() < LATENCY_SAMPLES {
+                    continue;
+                }
+                samples.iter().sum::<Duration>() / LATENCY_SAMPLES as u32
+            };
+            if average < stall_threshold() {
+                continue;
+            }
+            lock_state(&state).health_latencies.clear();
+            let msg = format!(
+                "[Warning] Backend health checks averaging {} ms, backend may be stalled",
+                average.as_millis()
+            );
+            eprintln!("{}", msg);
+            log_line(app, &msg);
+            set_status_title(app, Some("Backend Not Responding"));
+            let _ = app.emit_all("backend-stalled", average.as_millis() as u64);
+        } else {
+            failures += 1;
+            if failures < WATCHDOG_MAX_FAILURES {
+                continue;
+            }
+            let msg = format!(
+                "[Warning] Backend failed {} consecutive health checks",
+                failures
+            );
+            eprintln!("{}", msg);
+            log_line(app, &msg);
+            set_status_title(app, Some("Backend Offline"));
+            let _ = app.emit_all("backend-unhealthy", failures);
+        }
+
+        if std::env::var(WATCHDOG_RESTART_ENV).as_deref() == Ok("1") {
+            // Restarting replaces this watchdog, so hand off to another thread
+            // and exit rather than joining ourselves.
+            let app = app.clone();
+            thread::spawn(move || {
+                if let Err(e) = restart_backend_blocking(&app) {
+                    eprintln!("[Error] Backend restart failed: {}", e);
+                    log_line(&app, &format!("[Error] Backend restart failed: {e}"));
+                }
+            });
+            return;
+        }
+        failures = 0;
+    }
+}
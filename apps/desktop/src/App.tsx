@@ -15,12 +15,24 @@ import SettingsView from "./views/SettingsView";
 import HelpView from "./views/HelpView";
 import { ThemeProvider } from "./components/common/ThemeProvider";
 import { modelsApi, healthApi } from "./services/api";
+import { listenForCloseDuringIndexing, notifyFrontendReady } from "./utils/tauri";
 
 const App: React.FC = () => {
   const [currentPage, setCurrentPage] = useState("photos");
   const [showSetup, setShowSetup] = useState(false);
   const [checkingSetup, setCheckingSetup] = useState(true);
 
+  useEffect(() => {
+    notifyFrontendReady();
+  }, []);
+
+  useEffect(() => {
+    const unlisten = listenForCloseDuringIndexing();
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, []);
+
   // Check if setup is needed on startup
   useEffect(() => {
     const checkSetup = async () => {
@@ -3,7 +3,7 @@
  * Copyright (c) 2026 Abhishek Anand. Licensed under AGPL-3.0.
  */
 import React, { useState, useEffect, useRef } from "react";
-import { scanApi, healthApi, peopleApi, GlobalScanStatus } from "../services/api";
+import { scanApi, healthApi, peopleApi, objectsApi, placesApi, GlobalScanStatus } from "../services/api";
 import { openFolderDialog } from "../utils/tauri";
 import { useTheme } from "./common/ThemeProvider";
 import {
@@ -186,13 +186,9 @@ const Header: React.FC<HeaderProps> = ({ onSearch, onOpenSettings }) => {
       const cleanupResults = await Promise.allSettled([
         peopleApi.cleanupOrphans().then(r => ({ type: 'people', count: r.count })),
         
-        fetch('http://localhost:8000/objects/cleanup-orphans', { method: 'POST' })
-          .then(r => r.json())
-          .then(r => ({ type: 'objects', count: r.deleted_objects })),
+        objectsApi.cleanupOrphans().then(r => ({ type: 'objects', count: r.deleted_objects })),
         
-        fetch('http://localhost:8000/places/cleanup-orphans', { method: 'POST' })
-          .then(r => r.json())
-          .then(r => ({ type: 'locations', count: r.deleted_locations })),
+        placesApi.cleanupOrphans().then(r => ({ type: 'locations', count: r.deleted_locations })),
       ]);
       
       cleanupResults.forEach((result) => {
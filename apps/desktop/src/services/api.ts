@@ -2,23 +2,75 @@
  * PhotoSense-AI - https://github.com/abhishekanand16/PhotoSense-AI
  * Copyright (c) 2026 Abhishek Anand. Licensed under AGPL-3.0.
  */
-import axios from "axios";
-
-const API_BASE_URL = "http://localhost:8000";
+import axios, { AxiosAdapter, AxiosError, AxiosResponse } from "axios";
+import {
+  DEFAULT_BACKEND_URL,
+  getBackendBaseUrl,
+  getBackendToken,
+  isTauri,
+  proxyBackend,
+  proxyBackendImage,
+  ProxyResponse,
+} from "../utils/tauri";
 
 const api = axios.create({
-  baseURL: API_BASE_URL,
+  baseURL: DEFAULT_BACKEND_URL,
   headers: {
     "Content-Type": "application/json",
   },
   timeout: 30000, // 30 second timeout
 });
 
+// Under Tauri requests go through the shell rather than straight to a port,
+// so they keep working when the backend listens on a Unix socket.
+const proxyAdapter: AxiosAdapter = async (config) => {
+  const target = new URL(api.getUri(config));
+  const method = (config.method ?? "get").toUpperCase();
+  const body = typeof config.data === "string" ? config.data : undefined;
+  let proxied: ProxyResponse;
+  try {
+    proxied = await proxyBackend(method, `${target.pathname}${target.search}`, body);
+  } catch (error) {
+    throw new AxiosError(`Network Error: ${error}`, AxiosError.ERR_NETWORK, config);
+  }
+  const response: AxiosResponse = {
+    data: proxied.body,
+    status: proxied.status,
+    statusText: "",
+    headers: {},
+    config,
+  };
+  if (config.validateStatus && !config.validateStatus(response.status)) {
+    throw new AxiosError(
+      `Request failed with status code ${response.status}`,
+      AxiosError.ERR_BAD_RESPONSE,
+      config,
+      null,
+      response
+    );
+  }
+  return response;
+};
+
+api.interceptors.request.use(async (config) => {
+  if (isTauri()) {
+    config.adapter = proxyAdapter;
+    return config;
+  }
+  const token = await getBackendToken();
+  if (token) {
+    config.headers.Authorization = `Bearer ${token}`;
+  }
+  return config;
+});
+
 api.interceptors.response.use(
   (response) => response,
   (error) => {
     if (error.code === 'ECONNREFUSED' || error.message.includes('Network Error')) {
-      throw new Error('Cannot connect to backend API. Make sure the server is running at http://localhost:8000');
+      throw new Error(isTauri()
+        ? `Cannot connect to backend API: ${error.message}`
+        : `Cannot connect to backend API. Make sure the server is running at ${DEFAULT_BACKEND_URL}`);
     }
     if (error.response) {
       throw new Error(error.response.data?.detail || error.response.data?.message || `Server error: ${error.response.status}`);
@@ -72,7 +124,7 @@ export interface JobStatus {
 }
 
 export interface GlobalScanStatus {
-  status: "idle" | "scanning" | "indexing" | "completed" | "error";
+  status: "idle" | "scanning" | "indexing" | "completed" | "error" | "cancelled";
   total_photos: number;
   processed_photos: number;
   progress_percent: number;
@@ -188,13 +240,38 @@ export const photosApi = {
   },
 };
 
+let thumbnailObjectUrls: string[] = [];
+
+async function proxiedImageUrl(path: string): Promise<string | undefined> {
+  try {
+    const url = URL.createObjectURL(await proxyBackendImage(path));
+    thumbnailObjectUrls.push(url);
+    return url;
+  } catch {
+    return undefined;
+  }
+}
+
 export const peopleApi = {
   list: async (): Promise<Person[]> => {
     const response = await api.get<Person[]>("/people");
+    const baseUrl = await getBackendBaseUrl();
+    if (baseUrl === null) {
+      // The webview can't load images from a Unix socket, so fetch each
+      // thumbnail through the shell and hand the view an object URL.
+      thumbnailObjectUrls.forEach((url) => URL.revokeObjectURL(url));
+      thumbnailObjectUrls = [];
+      return Promise.all(response.data.map(async (person) => ({
+        ...person,
+        thumbnail_url: await proxiedImageUrl(`/people/${person.id}/thumbnail?size=200`),
+      })));
+    }
+    const token = await getBackendToken();
+    const tokenParam = token ? `&token=${encodeURIComponent(token)}` : "";
     // Add thumbnail URLs to each person
     return response.data.map(person => ({
       ...person,
-      thumbnail_url: `${API_BASE_URL}/people/${person.id}/thumbnail?size=200`
+      thumbnail_url: `${baseUrl}/people/${person.id}/thumbnail?size=200${tokenParam}`
     }));
   },
   getPhotos: async (personId: number): Promise<Photo[]> => {
@@ -285,6 +362,10 @@ export const objectsApi = {
     const response = await api.get<Photo[]>(`/objects/category/${category}/photos`);
     return response.data;
   },
+  cleanupOrphans: async (): Promise<{ deleted_objects: number }> => {
+    const response = await api.post("/objects/cleanup-orphans");
+    return response.data;
+  },
 };
 
 export const scenesApi = {
@@ -361,6 +442,11 @@ export const placesApi = {
     const response = await api.get<LocationStats>("/places/stats");
     return response.data;
   },
+
+  cleanupOrphans: async (): Promise<{ deleted_locations: number }> => {
+    const response = await api.post("/places/cleanup-orphans");
+    return response.data;
+  },
 };
 
 // Model status types for first-time setup
@@ -14,6 +14,95 @@ export function isTauri(): boolean {
   );
 }
 
+export const DEFAULT_BACKEND_URL = "http://localhost:8000";
+
+let backendTokenPromise: Promise<string | null> | null = null;
+
+export function getBackendToken(): Promise<string | null> {
+  if (!isTauri()) {
+    return Promise.resolve(null);
+  }
+  if (!backendTokenPromise) {
+    backendTokenPromise = import('@tauri-apps/api/tauri')
+      .then(({ invoke }) => invoke<string>('get_backend_token'))
+      .catch((error) => {
+        console.error('Failed to get backend token:', error);
+        backendTokenPromise = null;
+        return null;
+      });
+  }
+  return backendTokenPromise;
+}
+
+/**
+ * Base URL of the backend. The shell may have moved it off the default port
+ * when that was taken, so ask rather than assume; outside Tauri the default
+ * dev server is used. `null` when the backend is on a Unix socket and can
+ * only be reached through `proxyBackend`.
+ */
+export async function getBackendBaseUrl(): Promise<string | null> {
+  if (!isTauri()) {
+    return DEFAULT_BACKEND_URL;
+  }
+  try {
+    const { invoke } = await import('@tauri-apps/api/tauri');
+    return await invoke<string | null>('get_backend_url');
+  } catch (error) {
+    console.error('Failed to get backend URL:', error);
+    return DEFAULT_BACKEND_URL;
+  }
+}
+
+export interface ProxyResponse {
+  status: number;
+  body: string;
+}
+
+/**
+ * Sends a request through the shell, which attaches the auth token and
+ * follows the backend across restarts and port changes.
+ */
+export async function proxyBackend(method: string, path: string, body?: string): Promise<ProxyResponse> {
+  const { invoke } = await import('@tauri-apps/api/tauri');
+  return invoke<ProxyResponse>('proxy_backend', { method, path, body: body ?? null });
+}
+
+export async function proxyBackendImage(path: string): Promise<Blob> {
+  const { invoke } = await import('@tauri-apps/api/tauri');
+  const bytes = await invoke<number[]>('proxy_backend_image', { path });
+  return new Blob([new Uint8Array(bytes)]);
+}
+
+export async function notifyFrontendReady(): Promise<void> {
+  if (!isTauri()) {
+    return;
+  }
+  try {
+    const { invoke } = await import('@tauri-apps/api/tauri');
+    await invoke('frontend_ready');
+  } catch (error) {
+    console.error('Failed to report frontend ready:', error);
+  }
+}
+
+export async function listenForCloseDuringIndexing(): Promise<() => void> {
+  if (!isTauri()) {
+    return () => {};
+  }
+  const { listen } = await import('@tauri-apps/api/event');
+  return listen('confirm-close-during-indexing', async () => {
+    const { ask } = await import('@tauri-apps/api/dialog');
+    const quit = await ask('Indexing is still in progress. Quit anyway?', {
+      title: 'PhotoSense-AI',
+      type: 'warning',
+    });
+    if (quit) {
+      const { invoke } = await import('@tauri-apps/api/tauri');
+      await invoke('confirm_close');
+    }
+  });
+}
+
 export async function getTauriDialog() {
   if (!isTauri()) {
     return null;
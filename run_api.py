@@ -15,10 +15,13 @@ os.chdir(PROJECT_ROOT)
 if __name__ == "__main__":
     try:
         import uvicorn
+        # The desktop shell sets PHOTOSENSE_UDS_PATH when it wants a Unix socket
+        # instead of a TCP port.
         uvicorn.run(
             "services.api.main:app",
-            host="127.0.0.1",
-            port=8000,
+            host=os.environ.get("PHOTOSENSE_BIND_HOST", "127.0.0.1"),
+            port=int(os.environ.get("PHOTOSENSE_BACKEND_PORT", "8000")),
+            uds=os.environ.get("PHOTOSENSE_UDS_PATH") or None,
             reload=True
         )
     except ImportError:
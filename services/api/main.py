@@ -2,7 +2,13 @@
 # Copyright (c) 2026 Abhishek Anand. Licensed under AGPL-3.0.
 """FastAPI application entry point."""
 
+import asyncio
+import logging
 import os
+import secrets
+import signal
+import threading
+import time
 from PIL import Image
 
 # Configure PIL to support large images (up to 250MP)
@@ -27,9 +33,12 @@ for _k in ("OMP_NUM_THREADS", "MKL_NUM_THREADS", "OPENBLAS_NUM_THREADS", "VECLIB
 # HuggingFace tokenizers sometimes parallelize aggressively
 _set_default_env("TOKENIZERS_PARALLELISM", "false")
 
-from fastapi import FastAPI
+from fastapi import FastAPI, HTTPException, Request
 from fastapi.middleware.cors import CORSMiddleware
+from fastapi.responses import JSONResponse
 
+from services.config import APP_VERSION
+from services.logging_config import set_log_level
 from services.api.routes import faces, models, objects, people, pets, photos, places, scan, scenes, search, stats, tags
 
 app = FastAPI(
@@ -38,7 +47,29 @@ app = FastAPI(
     version="1.0.0",
 )
 
-# CORS middleware for desktop app
+# Token shared with the desktop shell; when set, every request must carry it
+_AUTH_TOKEN = os.environ.get("PHOTOSENSE_AUTH_TOKEN")
+# The API docs are opened in a regular browser, which has no way to send the
+# token; they expose only the schema, not library data
+_PUBLIC_PATHS = {"/docs", "/docs/oauth2-redirect", "/openapi.json"}
+
+
+@app.middleware("http")
+async def require_auth_token(request: Request, call_next):
+    """Reject requests without the desktop shell's token (if one is configured)."""
+    if _AUTH_TOKEN and request.method != "OPTIONS" and request.url.path not in _PUBLIC_PATHS:
+        header = request.headers.get("authorization", "")
+        if header.startswith("Bearer "):
+            supplied = header[len("Bearer "):]
+        else:
+            # <img> tags cannot send headers, so thumbnails pass it as a query param
+            supplied = request.query_params.get("token", "")
+        if not secrets.compare_digest(supplied, _AUTH_TOKEN):
+            return JSONResponse(status_code=401, content={"detail": "Unauthorized"})
+    return await call_next(request)
+
+
+# CORS middleware for desktop app (added last so it wraps the auth check)
 app.add_middleware(
     CORSMiddleware,
     allow_origins=["*"],  # In production, restrict to app origin
@@ -71,4 +102,90 @@ async def root():
 @app.get("/health")
 async def health():
     """Health check endpoint."""
-    return {"status": "healthy"}
+    return {"status": "healthy", "service": "photosense", "version": APP_VERSION, "pid": os.getpid()}
+
+
+def _check(ok: bool, detail=None) -> dict:
+    return {"ok": ok, "detail": detail}
+
+
+def _run_self_test() -> dict:
+    """Exercise each subsystem once; every check reports instead of raising."""
+    result = {"inference_latency_ms": None}
+
+    from services.ml.utils.model_tracker import get_model_tracker
+    overall = get_model_tracker().get_overall_progress()
+    models_ok = overall["all_ready"] and overall["models_error"] == 0
+    result["models"] = _check(models_ok, None if models_ok else (
+        f"{overall['models_ready']} ready, {overall['models_error']} failed, "
+        f"{overall['models_pending'] + overall['models_downloading']} pending"
+    ))
+
+    try:
+        from services.ml.storage.sqlite_store import SQLiteStore
+        stats = SQLiteStore(readonly=True).get_statistics()
+        result["database"] = _check(True, f"{stats.get('total_photos', 0)} photos")
+    except FileNotFoundError:
+        result["database"] = _check(True, "No library yet")
+    except Exception as e:
+        result["database"] = _check(False, str(e))
+
+    try:
+        import torch
+        from services.config import USE_GPU
+        if not USE_GPU:
+            result["gpu"] = _check(False, "Disabled by device preference")
+        elif torch.cuda.is_available():
+            result["gpu"] = _check(True, torch.cuda.get_device_name(0))
+        else:
+            result["gpu"] = _check(False, "No CUDA device available")
+    except Exception as e:
+        result["gpu"] = _check(False, str(e))
+
+    try:
+        from services.ml.embeddings.image_embedding import ImageEmbedder
+        started = time.monotonic()
+        ImageEmbedder().embed_text("a photo of a dog")
+        result["inference_latency_ms"] = int((time.monotonic() - started) * 1000)
+        result["inference"] = _check(True)
+    except Exception as e:
+        result["inference"] = _check(False, str(e))
+
+    # No GPU is a supported setup, so it does not fail the test.
+    result["ok"] = all(result[name]["ok"] for name in ("models", "database", "inference"))
+    return result
+
+
+@app.post("/selftest")
+async def self_test():
+    """Check models, database, GPU and a sample inference for the diagnostics panel."""
+    return await asyncio.to_thread(_run_self_test)
+
+
+_LOG_LEVELS = {
+    "debug": logging.DEBUG,
+    "info": logging.INFO,
+    "warn": logging.WARNING,
+    "error": logging.ERROR,
+}
+
+
+if _startup_level := os.environ.get("PHOTOSENSE_LOG_LEVEL", "").lower():
+    if _startup_level in _LOG_LEVELS:
+        set_log_level(_LOG_LEVELS[_startup_level])
+
+
+@app.post("/log-level")
+async def change_log_level(level: str):
+    """Change backend logging verbosity without a restart."""
+    if level.lower() not in _LOG_LEVELS:
+        raise HTTPException(status_code=400, detail=f"Unknown log level: {level}")
+    set_log_level(_LOG_LEVELS[level.lower()])
+    return {"status": "ok", "level": level.lower()}
+
+
+@app.post("/shutdown")
+async def shutdown():
+    """Ask the server to exit gracefully (used by the desktop shell on Windows)."""
+    threading.Timer(0.1, signal.raise_signal, args=(signal.SIGINT,)).start()
+    return {"status": "shutting_down"}
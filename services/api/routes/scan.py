@@ -25,6 +25,13 @@ _ml_executor = ThreadPoolExecutor(max_workers=2, thread_name_prefix="ml_worker")
 
 _jobs: Dict[str, Dict] = {}
 _jobs_lock = threading.Lock()
+# Jobs asked to stop; checked between photos (guarded by _jobs_lock)
+_cancel_requests: Set[str] = set()
+_TERMINAL_JOB_STATES = {"completed", "error", "cancelled"}
+
+
+class _JobCancelled(Exception):
+    """Raised inside a job once cancellation has been requested."""
 
 _global_scan_state: Dict = {
     "status": "idle",
@@ -115,6 +122,20 @@ def _create_job(job_id: str, initial_state: Dict) -> None:
         _jobs[job_id] = initial_state.copy()
 
 
+def _check_cancelled(job_id: str) -> None:
+    with _jobs_lock:
+        if job_id in _cancel_requests:
+            raise _JobCancelled()
+
+
+async def _finish_cancelled(job_id: str, pipeline) -> None:
+    """Record a cancelled job, keeping what was indexed so far."""
+    await asyncio.get_event_loop().run_in_executor(None, pipeline.index.save_all_dirty)
+    _update_job(job_id, status="cancelled", message="Cancelled")
+    _update_global_state(status="cancelled", message="Scan cancelled", eta_seconds=None)
+    logging.info(f"Job {job_id} cancelled")
+
+
 def _compute_eta_seconds(started_at: Optional[str], processed: int, total: int) -> Optional[int]:
     if not started_at or total <= 0 or processed <= 0:
         return None
@@ -227,6 +248,7 @@ async def process_folder_async(folder_path: str, recursive: bool, job_id: str):
         for _ in _iter_image_paths(folder, recursive, image_extensions):
             total += 1
             if total % 5000 == 0:
+                _check_cancelled(job_id)
                 await asyncio.sleep(0)
 
         _update_job(job_id, message=f"Found {total} images")
@@ -244,6 +266,7 @@ async def process_folder_async(folder_path: str, recursive: bool, job_id: str):
         imported_photos = []
         
         for idx, image_path in enumerate(_iter_image_paths(folder, recursive, image_extensions)):
+            _check_cancelled(job_id)
             try:
                 # Run import in thread pool - EXIF extraction is blocking
                 result = await loop.run_in_executor(
@@ -301,6 +324,7 @@ async def process_folder_async(folder_path: str, recursive: bool, job_id: str):
             batch = imported_photos[batch_start:batch_end]
             
             for photo_id, image_path in batch:
+                _check_cancelled(job_id)
                 try:
                     # Run ALL ML in thread pool - this is the key change
                     result = await loop.run_in_executor(
@@ -369,10 +393,15 @@ async def process_folder_async(folder_path: str, recursive: bool, job_id: str):
         )
         logging.info(f"Scan complete: {processed} photos, {total_faces} faces, {total_objects} objects, {clusters} clusters")
 
+    except _JobCancelled:
+        await _finish_cancelled(job_id, pipeline)
     except Exception as e:
         error_msg = f"Error: {str(e)}"
         _update_job(job_id, status="error", message=error_msg)
         _update_global_state(status="error", message="Scan failed", error=error_msg)
+    finally:
+        with _jobs_lock:
+            _cancel_requests.discard(job_id)
 
 
 @router.post("", response_model=ScanResponse)
@@ -443,6 +472,19 @@ async def get_job_status(job_id: str):
     )
 
 
+@router.post("/cancel/{job_id}")
+async def cancel_job(job_id: str):
+    """Ask a running job to stop after the photo it is on."""
+    job = _get_job(job_id)
+    if not job:
+        raise HTTPException(status_code=404, detail="Job not found")
+    if job["status"] in _TERMINAL_JOB_STATES:
+        return {"job_id": job_id, "status": job["status"], "cancelling": False}
+    with _jobs_lock:
+        _cancel_requests.add(job_id)
+    return {"job_id": job_id, "status": job["status"], "cancelling": True}
+
+
 async def scan_faces_async(job_id: str):
     """
     Re-scan all photos for faces and other ML features.
@@ -494,6 +536,7 @@ async def scan_faces_async(job_id: str):
         total_objects = 0
         
         for idx, photo in enumerate(photos):
+            _check_cancelled(job_id)
             try:
                 photo_id = photo["id"]
                 photo_path = photo["file_path"]
@@ -557,10 +600,15 @@ async def scan_faces_async(job_id: str):
         )
         logging.info(f"Face scan complete: {processed} photos, {total_faces} faces, {total_objects} objects, {clusters} clusters")
 
+    except _JobCancelled:
+        await _finish_cancelled(job_id, pipeline)
     except Exception as e:
         error_msg = f"Error: {str(e)}"
         _update_job(job_id, status="error", message=error_msg)
         _update_global_state(status="error", message="Scan failed", error=error_msg)
+    finally:
+        with _jobs_lock:
+            _cancel_requests.discard(job_id)
 
 
 @router.post("/faces", response_model=ScanResponse)
@@ -101,6 +101,10 @@ if env_log := os.environ.get("PHOTOSENSE_LOG_DIR"):
 if env_state := os.environ.get("PHOTOSENSE_STATE_DIR"):
     STATE_DIR = Path(env_state).resolve()
 
+# "cpu" forces CPU inference; "gpu" and "auto" use an accelerator when available.
+DEVICE_PREFERENCE = os.environ.get("PHOTOSENSE_DEVICE", "auto").strip().lower()
+USE_GPU = DEVICE_PREFERENCE != "cpu"
+
 SCAN_BATCH_SIZE = 8
 
 IMAGE_CACHE_SIZES: Dict[str, int] = {
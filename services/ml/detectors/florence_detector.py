@@ -10,6 +10,8 @@ import traceback
 import torch
 from PIL import Image
 
+from services.config import USE_GPU
+
 
 class FlorenceDetector:
     """
@@ -59,10 +61,10 @@ class FlorenceDetector:
     
     def _detect_device(self) -> Tuple[str, torch.dtype]:
         """Detect best available device and dtype."""
-        if torch.cuda.is_available():
+        if USE_GPU and torch.cuda.is_available():
             # NVIDIA GPU - float16 works well and saves memory
             return "cuda", torch.float16
-        elif torch.backends.mps.is_available():
+        elif USE_GPU and torch.backends.mps.is_available():
             # Apple Silicon (M1/M2) - must use float32 for Florence-2 compatibility
             # Florence-2 has layers that don't support float16 on MPS
             return "mps", torch.float32
@@ -12,6 +12,8 @@ from torchvision import transforms
 from PIL import Image
 import numpy as np
 
+from services.config import USE_GPU
+
 
 class SceneDetector:
     """Scene detection using Places365 ResNet50."""
@@ -48,7 +50,7 @@ class SceneDetector:
         self.confidence_threshold = confidence_threshold
         self.model = None
         self.labels = None
-        self.device = "cuda" if torch.cuda.is_available() else "cpu"
+        self.device = "cuda" if USE_GPU and torch.cuda.is_available() else "cpu"
         
         # Standard Places365 preprocessing
         self.transform = transforms.Compose([
@@ -10,6 +10,8 @@ import torch
 from PIL import Image
 from transformers import CLIPModel, CLIPProcessor
 
+from services.config import USE_GPU
+
 
 class ImageEmbedder:
     """Generate image embeddings using CLIP for semantic search."""
@@ -19,7 +21,7 @@ class ImageEmbedder:
         self.model_name = model_name
         self.model = None
         self.processor = None
-        self.device = "cuda" if torch.cuda.is_available() else "cpu"
+        self.device = "cuda" if USE_GPU and torch.cuda.is_available() else "cpu"
         # CLIP large model has 768 dimensions (more expressive than base's 512)
         self.embedding_dim = 768
 